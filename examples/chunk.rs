@@ -45,6 +45,7 @@ fn setup(
                 auto_geo: None,
                 visibility: Some(VoxelVisibility::Opaque),
                 has_item: None,
+                ..Default::default()
             },
         );
         registry.0.insert(
@@ -56,6 +57,7 @@ fn setup(
                 auto_geo: None,
                 visibility: Some(VoxelVisibility::Opaque),
                 has_item: None,
+                ..Default::default()
             },
         );
 
@@ -145,9 +147,10 @@ fn setup(
         let asset_registry = AssetRegistry::from_block_textures(textures, texture_handles);
 
         let mesh = full_mesh(
-            &ChunkBoundary::<BlockData, BlockRegistry>::new(
+            &ChunkBoundary::<BlockData, BlockRegistry>::new_at(
                 chunk,
                 Box::default(),
+                ChunkPos::new(0, 0, 0),
                 &registry,
                 &geo_table,
                 &asset_registry,
@@ -220,6 +220,28 @@ impl<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default> St
         }
     }
 
+    /// Index into `palette` of the voxel at idx. Voxels with the same index are the same
+    pub fn palette_index(&self, idx: usize) -> usize {
+        match self {
+            Storage::Single(_) => 0,
+            Storage::Multi(storage) => storage
+                .data
+                .get(idx * storage.indices_length, storage.indices_length),
+        }
+    }
+
+    /// Every voxel type in the storage. Can include types that are no longer used
+    pub fn palette(&self) -> Vec<&V> {
+        match self {
+            Storage::Single(storage) => vec![&storage.voxel],
+            Storage::Multi(storage) => storage
+                .palette
+                .iter()
+                .map(|entry| &entry.voxel_type)
+                .collect(),
+        }
+    }
+
     pub fn trim(&mut self) {
         match self {
             Storage::Single(_) => (),
@@ -286,6 +308,10 @@ impl<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default> De
 #[cfg_attr(feature = "bevy", derive(Component))]
 pub struct ChunkData<V: Voxel<R>, R: VoxRegistry<V>> {
     voxels: Storage<V, R>,
+    block_light: LightStorage,
+    sky_light: LightStorage,
+    colored_light: [LightStorage; 3],
+    lit: bool,
     change_count: u16,
     dirty: bool,
 }
@@ -296,6 +322,10 @@ impl<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default> De
     fn default() -> Self {
         Self {
             voxels: Storage::new(ChunkShape::USIZE),
+            block_light: LightStorage::default(),
+            sky_light: LightStorage::default(),
+            colored_light: Default::default(),
+            lit: false,
             change_count: 0,
             dirty: true,
         }
@@ -308,6 +338,15 @@ impl<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default> Ch
         self.voxels.get(Self::linearize(pos))
     }
 
+    /// Index into `palette` of the voxel at pos. Lets per voxel type work be done once instead of once per voxel
+    pub fn get_palette_index(&self, pos: RelativeVoxelPos) -> usize {
+        self.voxels.palette_index(Self::linearize(pos))
+    }
+
+    pub fn palette(&self) -> Vec<&V> {
+        self.voxels.palette()
+    }
+
     pub fn get_identifier(&self, pos: RelativeVoxelPos) -> String {
         let voxel = self.voxels.get(Self::linearize(pos));
         voxel.identifier()
@@ -324,6 +363,82 @@ impl<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default> Ch
         }
     }
 
+    pub fn get_block_light(&self, pos: RelativeVoxelPos) -> u8 {
+        self.block_light.get(Self::linearize(pos))
+    }
+
+    /// Marks the chunk dirty if the level changed since the chunk will need remeshing
+    pub fn set_block_light(&mut self, pos: RelativeVoxelPos, level: u8) {
+        let idx = Self::linearize(pos);
+        if self.block_light.get(idx) != level {
+            self.block_light.set(idx, level);
+            self.set_dirty(true);
+        }
+    }
+
+    pub fn block_light(&self) -> &LightStorage {
+        &self.block_light
+    }
+
+    pub fn block_light_mut(&mut self) -> &mut LightStorage {
+        &mut self.block_light
+    }
+
+    pub fn get_sky_light(&self, pos: RelativeVoxelPos) -> u8 {
+        self.sky_light.get(Self::linearize(pos))
+    }
+
+    /// Marks the chunk dirty if the level changed since the chunk will need remeshing
+    pub fn set_sky_light(&mut self, pos: RelativeVoxelPos, level: u8) {
+        let idx = Self::linearize(pos);
+        if self.sky_light.get(idx) != level {
+            self.sky_light.set(idx, level);
+            self.set_dirty(true);
+        }
+    }
+
+    pub fn sky_light(&self) -> &LightStorage {
+        &self.sky_light
+    }
+
+    pub fn sky_light_mut(&mut self) -> &mut LightStorage {
+        &mut self.sky_light
+    }
+
+    /// Red, green and blue light levels. Only used by the colored light mode
+    pub fn get_colored_light(&self, pos: RelativeVoxelPos) -> [u8; 3] {
+        let idx = Self::linearize(pos);
+        self.colored_light
+            .each_ref()
+            .map(|storage| storage.get(idx))
+    }
+
+    /// Sets a single color (0 red, 1 green, 2 blue). Marks the chunk dirty if the level changed
+    pub fn set_colored_light(&mut self, pos: RelativeVoxelPos, color: usize, level: u8) {
+        let idx = Self::linearize(pos);
+        if self.colored_light[color].get(idx) != level {
+            self.colored_light[color].set(idx, level);
+            self.set_dirty(true);
+        }
+    }
+
+    pub fn colored_light(&self) -> &[LightStorage; 3] {
+        &self.colored_light
+    }
+
+    pub fn colored_light_mut(&mut self) -> &mut [LightStorage; 3] {
+        &mut self.colored_light
+    }
+
+    /// Whether light has been calculated for this chunk. Chunks that aren't lit are meshed fully bright
+    pub fn is_lit(&self) -> bool {
+        self.lit
+    }
+
+    pub fn set_lit(&mut self, lit: bool) {
+        self.lit = lit;
+    }
+
     pub fn is_uniform(&self) -> bool {
         match self.voxels {
             Storage::Single(_) => true,
@@ -376,6 +491,10 @@ impl<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default> Ch
     pub fn from_raw(raw_chunk: RawChunk<V, R>) -> Self {
         Self {
             voxels: raw_chunk.voxels,
+            block_light: LightStorage::default(),
+            sky_light: LightStorage::default(),
+            colored_light: Default::default(),
+            lit: false,
             change_count: 0,
             dirty: false,
         }
@@ -129,3 +129,97 @@ impl Default for Geometry {
         }
     }
 }
+
+impl BlockGeo {
+    /// A single unrotated cube filling the whole voxel
+    pub fn is_full_cube(&self) -> bool {
+        self.rotation == (0, 0, 0)
+            && matches!(self.cubes.as_slice(), [cube] if cube.origin == (0, 0, 0)
+                && cube.end == (16, 16, 16)
+                && cube.rotation == (0, 0, 0))
+    }
+
+    /// Whether the mesher will rotate the given cube. Either by the block rotation or the cubes own rotation
+    pub fn is_rotated(&self, cube: usize) -> bool {
+        self.rotation != (0, 0, 0)
+            || self
+                .cubes
+                .get(cube)
+                .is_some_and(|cube| cube.rotation != (0, 0, 0))
+    }
+
+    /// Rotates a point in block space (0.0..1.0) by the block and then the cube rotation around their pivots. Matches the mesher
+    pub(crate) fn rotate_point(&self, cube: usize, point: glam::Vec3) -> glam::Vec3 {
+        let Some(face_descript) = self.cubes.get(cube) else {
+            return point;
+        };
+        let (pivot, rotation) = rotation_about(self.pivot, self.rotation);
+        let (pivot_cube, rotation_cube) =
+            rotation_about(face_descript.pivot, face_descript.rotation);
+        let point = pivot + rotation * (point - pivot);
+        pivot_cube + rotation_cube * (point - pivot_cube)
+    }
+
+    /// Inverse of rotate_point. Takes a point in block space back into the cubes unrotated space
+    #[cfg(feature = "physics")]
+    pub(crate) fn unrotate_point(&self, cube: usize, point: glam::Vec3) -> glam::Vec3 {
+        let Some(face_descript) = self.cubes.get(cube) else {
+            return point;
+        };
+        let (pivot, rotation) = rotation_about(self.pivot, self.rotation);
+        let (pivot_cube, rotation_cube) =
+            rotation_about(face_descript.pivot, face_descript.rotation);
+        let point = pivot_cube + rotation_cube.inverse() * (point - pivot_cube);
+        pivot + rotation.inverse() * (point - pivot)
+    }
+
+    /// Rotates a direction (such as a normal) by the block and cube rotation
+    pub(crate) fn rotate_direction(&self, cube: usize, direction: glam::Vec3) -> glam::Vec3 {
+        let Some(face_descript) = self.cubes.get(cube) else {
+            return direction;
+        };
+        rotation_about(face_descript.pivot, face_descript.rotation).1
+            * (rotation_about(self.pivot, self.rotation).1 * direction)
+    }
+
+    /// Inverse of rotate_direction
+    #[cfg(feature = "physics")]
+    pub(crate) fn unrotate_direction(&self, cube: usize, direction: glam::Vec3) -> glam::Vec3 {
+        let Some(face_descript) = self.cubes.get(cube) else {
+            return direction;
+        };
+        rotation_about(self.pivot, self.rotation).1.inverse()
+            * (rotation_about(face_descript.pivot, face_descript.rotation)
+                .1
+                .inverse()
+                * direction)
+    }
+}
+
+impl FaceDescript {
+    /// Minimum corner of this cube in block space (0.0..1.0)
+    pub(crate) fn min(&self) -> glam::Vec3 {
+        glam::Vec3::new(
+            self.origin.0 as f32,
+            self.origin.1 as f32,
+            self.origin.2 as f32,
+        ) / 16.0
+    }
+
+    /// Maximum corner of this cube in block space (0.0..1.0)
+    pub(crate) fn max(&self) -> glam::Vec3 {
+        glam::Vec3::new(self.end.0 as f32, self.end.1 as f32, self.end.2 as f32) / 16.0
+    }
+}
+
+fn rotation_about(pivot: (i8, i8, i8), rotation: (i8, i8, i8)) -> (glam::Vec3, glam::Quat) {
+    (
+        glam::Vec3::new(pivot.0 as f32, pivot.1 as f32, pivot.2 as f32) / 16.0,
+        glam::Quat::from_euler(
+            glam::EulerRot::XYZ,
+            (rotation.0 as f32).to_radians(),
+            (rotation.1 as f32).to_radians(),
+            (rotation.2 as f32).to_radians(),
+        ),
+    )
+}
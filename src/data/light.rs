@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// Brightest a light level can be. Levels are 4 bits
+pub const MAX_LIGHT: u8 = 15;
+
+/// 4 bit light levels for every voxel in a chunk packed two to a byte.
+/// Stays unallocated (every level reads as `fill`) until a level different from it is written
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LightStorage {
+    data: Option<Vec<u8>>,
+    fill: u8,
+}
+
+impl LightStorage {
+    /// Storage where every voxel has the same level without allocating
+    pub fn filled(level: u8) -> Self {
+        Self {
+            data: None,
+            fill: level.min(MAX_LIGHT),
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> u8 {
+        match &self.data {
+            Some(data) => (data[idx / 2] >> ((idx % 2) * 4)) & 0xF,
+            None => self.fill,
+        }
+    }
+
+    pub fn set(&mut self, idx: usize, level: u8) {
+        let level = level.min(MAX_LIGHT);
+        if self.data.is_none() {
+            if level == self.fill {
+                return;
+            }
+            self.data = Some(vec![self.fill | (self.fill << 4); TOTAL_CHUNK_SIZE / 2]);
+        }
+        if let Some(data) = &mut self.data {
+            let shift = (idx % 2) * 4;
+            data[idx / 2] = (data[idx / 2] & !(0xF << shift)) | (level << shift);
+        }
+    }
+
+    /// Sets every voxel to the same level and frees the storage
+    pub fn fill(&mut self, level: u8) {
+        self.data = None;
+        self.fill = level.min(MAX_LIGHT);
+    }
+
+    pub fn is_allocated(&self) -> bool {
+        self.data.is_some()
+    }
+}
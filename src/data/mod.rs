@@ -1,4 +1,6 @@
 pub mod chunk;
 pub mod geometry;
+pub mod light;
 pub mod position;
 pub mod voxel;
+pub mod world;
@@ -9,6 +9,22 @@ where
     Self: Sized,
 {
     fn is_empty(&self, vox: V) -> bool;
+    /// Physical properties of a voxel. Defaults to solid for anything that isn't empty
+    fn physics(&self, vox: V) -> BlockPhysics {
+        if self.is_empty(vox) {
+            BlockPhysics::passable()
+        } else {
+            BlockPhysics::default()
+        }
+    }
+    /// Light a voxel gives off. Defaults to none
+    fn emission(&self, _vox: V) -> LightEmission {
+        LightEmission::default()
+    }
+    /// How much of each color of colored light passes through a voxel. Defaults to all of it
+    fn light_filter(&self, _vox: V) -> [u8; 3] {
+        [MAX_LIGHT; 3]
+    }
 }
 
 pub trait Voxel<R: VoxRegistry<Self> + Sized>
@@ -23,6 +39,22 @@ where
     fn is_opaque(&self, registry: Option<&R>) -> bool;
     /// Identifier must be something that implements eq
     fn identifier(&self) -> String;
+    /// Physical properties used for collisions and gameplay. Empty voxels should be passable
+    fn physics(&self, registry: Option<&R>) -> BlockPhysics {
+        if self.is_empty(registry) {
+            BlockPhysics::passable()
+        } else {
+            BlockPhysics::default()
+        }
+    }
+    /// Light given off by this voxel. A level of 0 means it doesn't emit any
+    fn emission(&self, _registry: Option<&R>) -> LightEmission {
+        LightEmission::default()
+    }
+    /// How much of each color of colored light (0 to 15) passes through this voxel. Stained glass lets through only its own color
+    fn light_filter(&self, _registry: Option<&R>) -> [u8; 3] {
+        [MAX_LIGHT; 3]
+    }
 }
 
 #[derive(EnumString, Serialize, Deserialize, Debug, PartialEq, Eq, Default, Clone, Copy, Hash)]
@@ -31,6 +63,19 @@ pub enum VoxelVisibility {
     Empty,
     Opaque,
     Transparent,
+    /// Alpha tested voxels like leaves, grass and flowers. Culled like opaque voxels but drawn in their own mesh
+    /// whose shader discards transparent pixels. They don't block light
+    Cutout,
+}
+
+/// How a block with connected textures picks the tile of each face from the sheet of tiles its texture covers.
+/// Faces connect to neighbors with the same `RenderedBlockData::match_index` whose same face is also visible
+#[derive(EnumString, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum ConnectedTextures {
+    /// 47 tiles in an 8 by 6 sheet covering every combination of edges and corners. See `connected_tile`
+    Full,
+    /// 16 tiles in a 4 by 4 sheet that only look at the 4 edges of a face
+    Edges,
 }
 
 // Anything optional here that is necessary for the game to function but we have a default value for ie texture or geometry
@@ -49,6 +94,178 @@ pub struct Block {
     pub auto_geo: Option<Vec<BlockGeometry>>, // Contains strings of geometry we wan't to auto generate
     pub visibility: Option<VoxelVisibility>,
     pub has_item: Option<bool>, // Basically whether or not we should auto generate an item for this block                                // pub properties: Option<Vec<Box<BlockData>>>,
+    pub collision: Option<BlockCollision>, // Defaults to solid unless the block is empty
+    pub friction: Option<BlockFloat>,
+    pub blast_resistance: Option<BlockFloat>,
+    pub hardness: Option<BlockFloat>,
+    pub climbable: Option<bool>,
+    pub emission: Option<u8>, // Light level from 0 to 15 this block gives off
+    pub emission_color: Option<[u8; 3]>, // How much red, green and blue light from 0 to 15 is given off. Defaults to all of it
+    pub light_filter: Option<[u8; 3]>, // How much red, green and blue light from 0 to 15 passes through. Defaults to all of it
+    pub smooth: Option<bool>, // Mesh this block as part of a smooth surface instead of as blocks ie dirt or rock terrain
+    pub variants: Option<Vec<BlockVariant>>, // Weighted alternative looks picked per position ie several grass tops or rotated rocks
+    pub connected: Option<ConnectedTextures>, // Textures are sheets of tiles picked by matching neighbors ie glass or bookshelves
+}
+
+/// A float property of a block. Compared with `f32::total_cmp` so `Block` can stay `Eq`. Written as a plain number
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+#[serde(transparent)]
+pub struct BlockFloat(pub f32);
+
+impl PartialEq for BlockFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_cmp(&other.0).is_eq()
+    }
+}
+
+impl Eq for BlockFloat {}
+
+impl From<f32> for BlockFloat {
+    fn from(value: f32) -> Self {
+        Self(value)
+    }
+}
+
+/// An alternative look of a block. Every placed block picks one of its variants from its position so the same spot
+/// always looks the same. Anything left out is taken from the block itself
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Default, Clone)]
+pub struct BlockVariant {
+    pub weight: Option<u32>, // How likely this variant is compared to the others. Defaults to 1
+    pub geometry: Option<BlockGeometry>,
+    pub textures: Option<[Option<String>; 6]>, // Stored in the asset registry under `variant_texture_key`
+}
+
+/// The key the textures of a block variant are stored under in `AssetRegistry::texture_uvs`
+pub fn variant_texture_key(identifier: &str, variant: usize) -> String {
+    format!("{identifier}#{variant}")
+}
+
+/// How entities collide with a block
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Default, Clone, Hash)]
+pub enum BlockCollision {
+    /// Collides with the blocks render geometry
+    #[default]
+    Solid,
+    /// Nothing collides with this block ie tall grass or fluids
+    Passable,
+    /// Collides with these boxes instead of the render geometry
+    Custom(Vec<CollisionBox>),
+}
+
+/// A collision box in 1/16 of a block like `FaceDescript`
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Default, Clone, Copy, Hash)]
+pub struct CollisionBox {
+    pub origin: (i8, i8, i8),
+    pub end: (i8, i8, i8),
+}
+
+/// Physical properties of a block with the defaults filled in
+#[derive(Debug, PartialEq, Clone)]
+pub struct BlockPhysics {
+    pub collision: BlockCollision,
+    /// How much an entity slows down when moving over this block. Lower is more slippery
+    pub friction: f32,
+    pub blast_resistance: f32,
+    /// How long this block takes to break
+    pub hardness: f32,
+    /// Whether entities can climb this block like a ladder
+    pub climbable: bool,
+}
+
+impl Default for BlockPhysics {
+    fn default() -> Self {
+        Self {
+            collision: BlockCollision::Solid,
+            friction: 0.6,
+            blast_resistance: 0.0,
+            hardness: 0.0,
+            climbable: false,
+        }
+    }
+}
+
+impl BlockPhysics {
+    pub fn passable() -> Self {
+        Self {
+            collision: BlockCollision::Passable,
+            ..Default::default()
+        }
+    }
+}
+
+/// Light a voxel gives off with the defaults filled in
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct LightEmission {
+    pub level: u8,
+    /// Tint of the light from 0 to 15 per color. Uses the same scale as `Voxel::light_filter`
+    pub color: [u8; 3],
+}
+
+impl Default for LightEmission {
+    fn default() -> Self {
+        Self {
+            level: 0,
+            color: [MAX_LIGHT; 3],
+        }
+    }
+}
+
+impl Block {
+    /// Weight of every variant in order. Empty if the block has no variants
+    pub fn variant_weights(&self) -> Vec<u32> {
+        self.variants
+            .iter()
+            .flatten()
+            .map(|variant| variant.weight.unwrap_or(1))
+            .collect()
+    }
+
+    /// Geometry of the given variant or of the block itself
+    pub fn variant_geometry(&self, variant: Option<usize>) -> BlockGeometry {
+        variant
+            .and_then(|variant| self.variants.as_ref()?.get(variant)?.geometry.clone())
+            .or_else(|| self.geometry.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn emission(&self) -> LightEmission {
+        LightEmission {
+            level: self.emission.unwrap_or_default().min(MAX_LIGHT),
+            color: self
+                .emission_color
+                .unwrap_or(LightEmission::default().color)
+                .map(|color| color.min(MAX_LIGHT)),
+        }
+    }
+
+    pub fn light_filter(&self) -> [u8; 3] {
+        self.light_filter
+            .unwrap_or([MAX_LIGHT; 3])
+            .map(|filter| filter.min(MAX_LIGHT))
+    }
+
+    pub fn physics(&self) -> BlockPhysics {
+        let default = BlockPhysics::default();
+        BlockPhysics {
+            collision: self.collision.clone().unwrap_or(
+                if self.visibility.unwrap_or_default() == VoxelVisibility::Empty {
+                    BlockCollision::Passable
+                } else {
+                    BlockCollision::Solid
+                },
+            ),
+            friction: self
+                .friction
+                .map_or(default.friction, |friction| friction.0),
+            blast_resistance: self
+                .blast_resistance
+                .map_or(default.blast_resistance, |resistance| resistance.0),
+            hardness: self
+                .hardness
+                .map_or(default.hardness, |hardness| hardness.0),
+            climbable: self.climbable.unwrap_or(default.climbable),
+        }
+    }
 }
 
 #[derive(Deref, DerefMut, Default, Clone, Serialize, Deserialize)]
@@ -67,6 +284,10 @@ pub struct UVRect {
 pub struct AssetRegistry {
     pub texture_uvs: HashMap<String, [UVRect; 6]>,
     pub texture_size: mint::Point2<f32>,
+    /// Layer of every face in a texture array for meshing with `TextureMode::Layers`. Uses the same keys and face
+    /// order as `texture_uvs`
+    #[serde(default)]
+    pub texture_layers: HashMap<String, [u32; 6]>,
     // pub texture_atlas: TextureAtlas,
 }
 
@@ -116,6 +337,28 @@ impl VoxRegistry<BlockData> for BlockRegistry {
             true
         }
     }
+
+    fn physics(&self, vox: BlockData) -> BlockPhysics {
+        if let Some(voxel) = self.0.get(&vox.identifier) {
+            voxel.physics()
+        } else {
+            BlockPhysics::passable()
+        }
+    }
+
+    fn emission(&self, vox: BlockData) -> LightEmission {
+        self.0
+            .get(&vox.identifier)
+            .map(|voxel| voxel.emission())
+            .unwrap_or_default()
+    }
+
+    fn light_filter(&self, vox: BlockData) -> [u8; 3] {
+        self.0
+            .get(&vox.identifier)
+            .map(|voxel| voxel.light_filter())
+            .unwrap_or([MAX_LIGHT; 3])
+    }
 }
 
 #[cfg(feature = "block")]
@@ -137,6 +380,29 @@ pub struct BlockData {
     pub properties: Option<Vec<(String, Property)>>,
 }
 
+#[cfg(feature = "block")]
+impl BlockData {
+    /// Looks up this blocks textures in one of the asset registries maps. Variants without their own textures use
+    /// the blocks
+    fn variant_texture<T: Copy>(
+        &self,
+        textures: &HashMap<String, T>,
+        vox_registry: Option<&BlockRegistry>,
+        variant: Option<usize>,
+    ) -> Option<T> {
+        let variant_textures = variant.filter(|variant| {
+            vox_registry
+                .and_then(|registry| registry.get(&self.identifier))
+                .and_then(|block| block.variants.as_ref()?.get(*variant))
+                .is_some_and(|variant| variant.textures.is_some())
+        });
+        variant_textures
+            .and_then(|variant| textures.get(&variant_texture_key(&self.identifier, variant)))
+            .or_else(|| textures.get(&self.identifier))
+            .copied()
+    }
+}
+
 #[cfg(feature = "block")]
 impl RenderedVoxel<Self, BlockRegistry> for BlockData {
     fn to_geo_idx(
@@ -144,18 +410,23 @@ impl RenderedVoxel<Self, BlockRegistry> for BlockData {
         geo_pal: Option<&mut crate::mesh::chunk::GeoPalette>,
         geo_registry: Option<&GeometryRegistry>,
         vox_registry: Option<&BlockRegistry>,
+    ) -> Option<usize> {
+        self.to_geo_idx_variant(geo_pal, geo_registry, vox_registry, None)
+    }
+
+    fn to_geo_idx_variant(
+        &self,
+        geo_pal: Option<&mut crate::mesh::chunk::GeoPalette>,
+        geo_registry: Option<&GeometryRegistry>,
+        vox_registry: Option<&BlockRegistry>,
+        variant: Option<usize>,
     ) -> Option<usize> {
         if let Some(geo_registry) = geo_registry {
             if let Some(vox_registry) = vox_registry {
                 if let Some(block_data) = vox_registry.get(&self.identifier) {
                     if let Some(geo_pal) = geo_pal {
-                        let geo_data = geo_registry.get(
-                            &block_data
-                                .clone()
-                                .geometry
-                                .unwrap_or_default()
-                                .get_geo_namespace(),
-                        );
+                        let geo_data = geo_registry
+                            .get(&block_data.variant_geometry(variant).get_geo_namespace());
 
                         let geo_data_new = geo_data.unwrap().element.clone();
                         return Some(if geo_pal.palette.contains(&geo_data_new) {
@@ -205,30 +476,54 @@ impl RenderedVoxel<Self, BlockRegistry> for BlockData {
 
     fn to_texture_uv(
         &self,
-        _vox_registry: Option<&BlockRegistry>,
+        vox_registry: Option<&BlockRegistry>,
         asset_registry: Option<&AssetRegistry>,
     ) -> Option<[UVRect; 6]> {
-        if let Some(asset_registry) = asset_registry {
-            return asset_registry.texture_uvs.get(&self.identifier).copied();
-        }
-        None
+        self.to_texture_uv_variant(vox_registry, asset_registry, None)
+    }
+
+    fn to_texture_uv_variant(
+        &self,
+        vox_registry: Option<&BlockRegistry>,
+        asset_registry: Option<&AssetRegistry>,
+        variant: Option<usize>,
+    ) -> Option<[UVRect; 6]> {
+        asset_registry.and_then(|asset_registry| {
+            self.variant_texture(&asset_registry.texture_uvs, vox_registry, variant)
+        })
+    }
+
+    fn to_texture_layers(
+        &self,
+        vox_registry: Option<&BlockRegistry>,
+        asset_registry: Option<&AssetRegistry>,
+        variant: Option<usize>,
+    ) -> Option<[u32; 6]> {
+        asset_registry.and_then(|asset_registry| {
+            self.variant_texture(&asset_registry.texture_layers, vox_registry, variant)
+        })
     }
 
     fn blocking_sides(
         &self,
         vox_registry: Option<&BlockRegistry>,
         geo_registry: Option<&GeometryRegistry>,
+    ) -> Option<([bool; 6], Option<[bool; 6]>)> {
+        self.blocking_sides_variant(vox_registry, geo_registry, None)
+    }
+
+    fn blocking_sides_variant(
+        &self,
+        vox_registry: Option<&BlockRegistry>,
+        geo_registry: Option<&GeometryRegistry>,
+        variant: Option<usize>,
     ) -> Option<([bool; 6], Option<[bool; 6]>)> {
         if let Some(geo_registry) = geo_registry {
             if let Some(vox_registry) = vox_registry {
                 if let Some(block_data) = vox_registry.get(&self.identifier) {
-                    if let Some(geo_data) = geo_registry.get(
-                        &block_data
-                            .clone()
-                            .geometry
-                            .unwrap_or_default()
-                            .get_geo_namespace(),
-                    ) {
+                    if let Some(geo_data) =
+                        geo_registry.get(&block_data.variant_geometry(variant).get_geo_namespace())
+                    {
                         return Some((geo_data.blocks, geo_data.blocks_self));
                     }
                 }
@@ -237,8 +532,26 @@ impl RenderedVoxel<Self, BlockRegistry> for BlockData {
         None
     }
 
-    fn light_level() -> Option<u8> {
-        None
+    fn variant_weights(&self, vox_registry: Option<&BlockRegistry>) -> Vec<u32> {
+        vox_registry
+            .and_then(|registry| registry.get(&self.identifier))
+            .map(Block::variant_weights)
+            .unwrap_or_default()
+    }
+
+    fn connected_textures(
+        &self,
+        vox_registry: Option<&BlockRegistry>,
+    ) -> Option<ConnectedTextures> {
+        vox_registry
+            .and_then(|registry| registry.get(&self.identifier))
+            .and_then(|block| block.connected)
+    }
+
+    fn is_smooth(&self, vox_registry: Option<&BlockRegistry>) -> bool {
+        vox_registry
+            .and_then(|registry| registry.get(&self.identifier))
+            .is_some_and(|block| block.smooth.unwrap_or(false))
     }
 
     fn to_visibility(
@@ -290,6 +603,26 @@ impl Voxel<BlockRegistry> for BlockData {
     fn identifier(&self) -> String {
         self.identifier.clone()
     }
+
+    fn physics(&self, registry: Option<&BlockRegistry>) -> BlockPhysics {
+        if let Some(registry) = registry {
+            registry.physics(self.clone())
+        } else {
+            BlockPhysics::passable()
+        }
+    }
+
+    fn emission(&self, registry: Option<&BlockRegistry>) -> LightEmission {
+        registry
+            .map(|registry| registry.emission(self.clone()))
+            .unwrap_or_default()
+    }
+
+    fn light_filter(&self, registry: Option<&BlockRegistry>) -> [u8; 3] {
+        registry
+            .map(|registry| registry.light_filter(self.clone()))
+            .unwrap_or([MAX_LIGHT; 3])
+    }
 }
 
 #[cfg(feature = "block")]
@@ -312,3 +645,185 @@ impl BlockData {
         }
     }
 }
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    // Implements only the required methods so the defaults are used
+    #[derive(Clone, Copy, Default, PartialEq, Eq, Serialize)]
+    struct Minimal(bool);
+
+    #[derive(Clone, Default)]
+    struct MinimalRegistry;
+
+    impl VoxRegistry<Minimal> for MinimalRegistry {
+        fn is_empty(&self, vox: Minimal) -> bool {
+            !vox.0
+        }
+    }
+
+    impl Voxel<MinimalRegistry> for Minimal {
+        fn is_empty(&self, _registry: Option<&MinimalRegistry>) -> bool {
+            !self.0
+        }
+
+        fn is_true_empty(&self, registry: Option<&MinimalRegistry>) -> bool {
+            self.is_empty(registry)
+        }
+
+        fn is_opaque(&self, _registry: Option<&MinimalRegistry>) -> bool {
+            self.0
+        }
+
+        fn identifier(&self) -> String {
+            String::new()
+        }
+    }
+
+    #[cfg(feature = "render")]
+    impl RenderedVoxel<Minimal, MinimalRegistry> for Minimal {
+        fn to_geo_idx(
+            &self,
+            _geo_pal: Option<&mut crate::mesh::chunk::GeoPalette>,
+            _geo_registry: Option<&GeometryRegistry>,
+            _vox_registry: Option<&MinimalRegistry>,
+        ) -> Option<usize> {
+            self.0.then_some(0)
+        }
+
+        fn to_match_idx(&self, _match_pal: Option<&mut crate::mesh::chunk::BlockMatches>) -> usize {
+            0
+        }
+
+        fn to_texture_uv(
+            &self,
+            _vox_registry: Option<&MinimalRegistry>,
+            _asset_registry: Option<&AssetRegistry>,
+        ) -> Option<[UVRect; 6]> {
+            None
+        }
+
+        fn blocking_sides(
+            &self,
+            _vox_registry: Option<&MinimalRegistry>,
+            _geo_registry: Option<&GeometryRegistry>,
+        ) -> Option<([bool; 6], Option<[bool; 6]>)> {
+            Some(([self.0; 6], None))
+        }
+
+        fn to_visibility(
+            &self,
+            _vox_registry: Option<&MinimalRegistry>,
+            _geo_registry: Option<&GeometryRegistry>,
+        ) -> Option<VoxelVisibility> {
+            Some(if self.0 {
+                VoxelVisibility::Opaque
+            } else {
+                VoxelVisibility::Empty
+            })
+        }
+    }
+
+    #[test]
+    fn default_physics() {
+        assert_eq!(Minimal(true).physics(None), BlockPhysics::default());
+        assert_eq!(
+            Minimal(false).physics(None).collision,
+            BlockCollision::Passable
+        );
+        assert_eq!(
+            MinimalRegistry.physics(Minimal(true)),
+            BlockPhysics::default()
+        );
+    }
+
+    #[test]
+    fn block_physics() {
+        let ice: Block = ron::from_str(
+            r#"(
+                identifier: "vinox:ice",
+                textures: None,
+                geometry: None,
+                auto_geo: None,
+                visibility: Some(Opaque),
+                has_item: None,
+                friction: Some(0.98),
+                collision: Some(Custom([(origin: (0, 0, 0), end: (16, 4, 16))])),
+            )"#,
+        )
+        .unwrap();
+        assert_eq!(ice.friction, Some(BlockFloat(0.98)));
+        assert_ne!(ice, Block::default());
+        let physics = ice.physics();
+        assert_eq!(physics.friction, 0.98);
+        assert_eq!(physics.hardness, BlockPhysics::default().hardness);
+        assert_eq!(
+            physics.collision,
+            BlockCollision::Custom(vec![CollisionBox {
+                origin: (0, 0, 0),
+                end: (16, 4, 16),
+            }])
+        );
+
+        let (mut registry, _, _) = registries();
+        registry.insert("vinox:ice".into(), ice);
+        assert_eq!(block("ice").physics(Some(&registry)).friction, 0.98);
+        assert_eq!(
+            block("air").physics(Some(&registry)).collision,
+            BlockCollision::Passable
+        );
+        assert_eq!(
+            registry.physics(block("stone")).collision,
+            BlockCollision::Solid
+        );
+        // Unknown blocks are passable
+        assert_eq!(
+            block("missing").physics(Some(&registry)).collision,
+            BlockCollision::Passable
+        );
+    }
+
+    #[test]
+    fn emission() {
+        assert_eq!(Minimal(true).emission(None), LightEmission::default());
+        assert_eq!(MinimalRegistry.emission(Minimal(true)).level, 0);
+        assert_eq!(Minimal(true).light_filter(None), [MAX_LIGHT; 3]);
+        assert_eq!(MinimalRegistry.light_filter(Minimal(true)), [MAX_LIGHT; 3]);
+
+        let (mut registry, _, _) = registries();
+        let glowstone = Block {
+            identifier: "vinox:glowstone".into(),
+            visibility: Some(VoxelVisibility::Opaque),
+            emission: Some(20),
+            emission_color: Some([255, 255, 255]),
+            ..Default::default()
+        };
+        registry.insert("vinox:glowstone".into(), glowstone);
+        // Levels are clamped to the highest light level
+        assert_eq!(
+            block("glowstone").emission(Some(&registry)).level,
+            MAX_LIGHT
+        );
+        assert_eq!(block("stone").emission(Some(&registry)).level, 0);
+        assert_eq!(block("glowstone").emission(None).level, 0);
+    }
+
+    #[cfg(feature = "render")]
+    #[test]
+    fn rendered_defaults() {
+        let voxel = Minimal(true);
+        assert!(voxel.variant_weights(None).is_empty());
+        assert!(!voxel.is_smooth(None));
+        assert_eq!(voxel.connected_textures(None), None);
+        assert_eq!(voxel.to_texture_layers(None, None, None), None);
+        // Variants fall back to the voxel itself
+        assert_eq!(voxel.to_geo_idx_variant(None, None, None, Some(1)), Some(0));
+        assert_eq!(
+            voxel.blocking_sides_variant(None, None, Some(1)),
+            voxel.blocking_sides(None, None)
+        );
+        assert_eq!(voxel.to_texture_uv_variant(None, None, Some(1)), None);
+    }
+}
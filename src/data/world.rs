@@ -0,0 +1,57 @@
+use ahash::HashMap;
+use derive_more::{Deref, DerefMut};
+
+use crate::prelude::*;
+
+/// A collection of loaded chunks addressed by their chunk position
+#[derive(Deref, DerefMut, Clone)]
+pub struct ChunkMap<V: Voxel<R>, R: VoxRegistry<V>>(pub HashMap<ChunkPos, ChunkData<V, R>>);
+
+impl<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default> Default
+    for ChunkMap<V, R>
+{
+    fn default() -> Self {
+        Self(HashMap::default())
+    }
+}
+
+impl<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default> ChunkMap<V, R> {
+    /// Returns the voxel at a world position or None if its chunk isn't loaded
+    pub fn get_voxel(&self, pos: VoxelPos) -> Option<V> {
+        let (relative, chunk_pos) = pos.to_offsets();
+        self.0.get(&chunk_pos).map(|chunk| chunk.get(relative))
+    }
+
+    /// Sets the voxel at a world position. Returns false if its chunk isn't loaded
+    pub fn set_voxel(&mut self, pos: VoxelPos, voxel: V) -> bool {
+        let (relative, chunk_pos) = pos.to_offsets();
+        if let Some(chunk) = self.0.get_mut(&chunk_pos) {
+            chunk.set(relative, voxel);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Clones the 26 neighbors of a chunk in the order of `ChunkPos::neighbors`. Returns None if any are missing
+    pub fn neighbors(&self, pos: ChunkPos) -> Option<[ChunkData<V, R>; 26]> {
+        let neighbors = pos
+            .neighbors()
+            .iter()
+            .map(|neighbor| self.0.get(neighbor).cloned())
+            .collect::<Option<Vec<_>>>()?;
+        neighbors.try_into().ok()
+    }
+
+    /// Same as neighbors but missing chunks are replaced with default (empty) chunks
+    pub fn neighbors_or_default(&self, pos: ChunkPos) -> [ChunkData<V, R>; 26] {
+        let neighbors = pos
+            .neighbors()
+            .iter()
+            .map(|neighbor| self.0.get(neighbor).cloned().unwrap_or_default())
+            .collect::<Vec<_>>();
+        neighbors
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("a chunk always has 26 neighbors"))
+    }
+}
@@ -1,13 +1,35 @@
 pub mod block;
 pub mod data;
+#[cfg(feature = "light")]
+pub mod light;
 pub mod mesh;
+#[cfg(feature = "physics")]
+pub mod physics;
+#[cfg(feature = "render")]
+pub mod pipeline;
 pub mod scripting;
+#[cfg(all(test, feature = "block"))]
+mod test_util;
 
 pub mod prelude {
     pub use crate::data::chunk::*;
     pub use crate::data::geometry::*;
+    pub use crate::data::light::*;
     pub use crate::data::position::*;
     pub use crate::data::voxel::*;
+    pub use crate::data::world::*;
+    #[cfg(feature = "light")]
+    pub use crate::light::{block::*, colored::*, sky::*, *};
+    pub use crate::mesh::batch::*;
     pub use crate::mesh::chunk::*;
+    pub use crate::mesh::greedy::*;
+    pub use crate::mesh::lod::*;
     pub use crate::mesh::mesher::*;
+    pub use crate::mesh::outline::*;
+    pub use crate::mesh::packed::*;
+    pub use crate::mesh::smooth::*;
+    #[cfg(feature = "physics")]
+    pub use crate::physics::{collision::*, raycast::*};
+    #[cfg(feature = "render")]
+    pub use crate::pipeline::{pool::*, *};
 }
@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+
+use super::{
+    border_voxels, get_light, offset, propagate, set_light, transmits_light, unpropagate,
+    LightChannel, NEIGHBOR_OFFSETS,
+};
+use crate::prelude::*;
+
+/// Lights a chunk from scratch. Clears its block light, floods light from every voxel that emits some and pulls
+/// in light from the neighboring chunks so light crosses chunk borders. Light also spreads into loaded neighbors.
+/// How much light a voxel gives off comes from `Voxel::emission`
+pub fn propagate_block_light<
+    V: Voxel<R> + Clone + Eq + Default,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &mut ChunkMap<V, R>,
+    registry: &R,
+    chunk_pos: ChunkPos,
+) {
+    relight_chunk(world, registry, chunk_pos, LightChannel::Block, |voxel| {
+        voxel.emission(Some(registry)).level
+    });
+}
+
+/// Call after the voxel at pos was changed (placed, broken or its emission changed). Removes the light that the old
+/// voxel let through or gave off then relights the area from the new voxel and any remaining light around it
+pub fn update_block_light<
+    V: Voxel<R> + Clone + Eq + Default,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &mut ChunkMap<V, R>,
+    registry: &R,
+    pos: VoxelPos,
+) {
+    relight_voxel(world, registry, pos, LightChannel::Block, |voxel| {
+        voxel.emission(Some(registry)).level
+    });
+}
+
+/// Clears a channel in a chunk then floods it from every emitting voxel and from lit voxels just outside the chunk
+pub(crate) fn relight_chunk<
+    V: Voxel<R> + Clone + Eq + Default,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &mut ChunkMap<V, R>,
+    registry: &R,
+    chunk_pos: ChunkPos,
+    channel: LightChannel,
+    emission: impl Fn(&V) -> u8,
+) {
+    if !world.contains_key(&chunk_pos) {
+        return;
+    }
+    // Take back the light the old contents spread into the neighbors before clearing, or it would be left behind
+    let lit = (0..ChunkData::<V, R>::usize())
+        .map(|i| VoxelPos::from((ChunkData::<V, R>::delinearize(i), chunk_pos)))
+        .filter_map(|pos| {
+            get_light(world, pos, channel).and_then(|level| (level > 0).then_some((pos, level)))
+        })
+        .collect::<VecDeque<_>>();
+    for (pos, _) in &lit {
+        set_light(world, *pos, channel, 0);
+    }
+    let relit = unpropagate(world, channel, lit, &emission);
+
+    let Some(chunk) = world.get_mut(&chunk_pos) else {
+        return;
+    };
+    match channel.color_index() {
+        Some(color) => chunk.colored_light_mut()[color].fill(0),
+        None => chunk.block_light_mut().fill(0),
+    }
+    chunk.set_lit(true);
+    chunk.set_dirty(true);
+
+    let emitters = (0..ChunkData::<V, R>::usize())
+        .map(ChunkData::<V, R>::delinearize)
+        .filter_map(|relative| {
+            let emitted = emission(&chunk.get(relative));
+            (emitted > 0).then(|| (VoxelPos::from((relative, chunk_pos)), emitted))
+        })
+        .collect::<Vec<_>>();
+
+    let mut queue = VecDeque::new();
+    for (pos, emitted) in emitters {
+        set_light(world, pos, channel, emitted);
+        queue.push_back(pos);
+    }
+
+    // Voxels just outside the chunk that are lit spread back in
+    queue.extend(relit);
+    queue.extend(
+        border_voxels(chunk_pos)
+            .filter(|pos| get_light(world, *pos, channel).is_some_and(|level| level > 1)),
+    );
+
+    propagate(world, registry, channel, queue);
+}
+
+/// Removes the light a changed voxel gave off or let through then relights around it
+pub(crate) fn relight_voxel<
+    V: Voxel<R> + Clone + Eq + Default,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &mut ChunkMap<V, R>,
+    registry: &R,
+    pos: VoxelPos,
+    channel: LightChannel,
+    emission: impl Fn(&V) -> u8,
+) {
+    let Some(voxel) = world.get_voxel(pos) else {
+        return;
+    };
+    let old_level = get_light(world, pos, channel).unwrap_or_default();
+
+    set_light(world, pos, channel, 0);
+    let mut relight = unpropagate(
+        world,
+        channel,
+        VecDeque::from([(pos, old_level)]),
+        &emission,
+    );
+
+    let emitted = emission(&voxel);
+    if emitted > 0 {
+        set_light(world, pos, channel, emitted);
+        relight.push_back(pos);
+    }
+    if transmits_light(&voxel, registry) {
+        // Let the surrounding light back in
+        for neighbor_offset in NEIGHBOR_OFFSETS {
+            let neighbor = offset(pos, neighbor_offset);
+            if get_light(world, neighbor, channel).is_some_and(|level| level > 1) {
+                relight.push_back(neighbor);
+            }
+        }
+    }
+
+    propagate(world, registry, channel, relight);
+}
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    // A 3x3x3 block of empty chunks around the origin with an emitter just inside the +x border of the center chunk
+    fn world() -> (ChunkMap<BlockData, BlockRegistry>, BlockRegistry) {
+        let (mut registry, _, _) = registries();
+        registry.get_mut("vinox:glass").unwrap().emission = Some(14);
+        let mut world = ChunkMap::default();
+        let chunks = ChunkPos::new(0, 0, 0).neighbors();
+        for pos in chunks.iter().chain([ChunkPos::new(0, 0, 0)].iter()) {
+            world.insert(*pos, ChunkData::default());
+        }
+        world.set_voxel(VoxelPos::new(15, 5, 5), block("glass"));
+        for pos in chunks.iter().chain([ChunkPos::new(0, 0, 0)].iter()) {
+            propagate_block_light(&mut world, &registry, *pos);
+        }
+        (world, registry)
+    }
+
+    fn light(world: &ChunkMap<BlockData, BlockRegistry>, x: i32, y: i32, z: i32) -> u8 {
+        get_light(world, VoxelPos::new(x, y, z), LightChannel::Block).unwrap()
+    }
+
+    fn set(
+        world: &mut ChunkMap<BlockData, BlockRegistry>,
+        registry: &BlockRegistry,
+        pos: VoxelPos,
+        name: &str,
+    ) {
+        world.set_voxel(pos, block(name));
+        update_block_light(world, registry, pos);
+    }
+
+    #[test]
+    fn spreads_across_chunks() {
+        let (world, _) = world();
+        assert_eq!(light(&world, 15, 5, 5), 14);
+        assert_eq!(light(&world, 16, 5, 5), 13);
+        assert_eq!(light(&world, 20, 5, 5), 9);
+        assert_eq!(light(&world, 15, 5, 8), 11);
+        assert_eq!(light(&world, 15, -1, 5), 8);
+    }
+
+    #[test]
+    fn walls_and_removal() {
+        let (mut world, registry) = world();
+        for y in -16..32 {
+            for z in -16..32 {
+                set(&mut world, &registry, VoxelPos::new(17, y, z), "stone");
+            }
+        }
+        assert_eq!(light(&world, 17, 5, 5), 0);
+        assert_eq!(light(&world, 18, 5, 5), 0);
+        assert_eq!(light(&world, 16, 5, 5), 13);
+
+        set(&mut world, &registry, VoxelPos::new(15, 5, 5), "air");
+        for x in 0..17 {
+            assert_eq!(light(&world, x, 5, 5), 0);
+        }
+
+        // Light comes back through a hole in the wall
+        set(&mut world, &registry, VoxelPos::new(15, 5, 5), "glass");
+        set(&mut world, &registry, VoxelPos::new(17, 5, 5), "air");
+        assert_eq!(light(&world, 18, 5, 5), 11);
+
+        // Removing one of two emitters keeps the light of the other
+        set(&mut world, &registry, VoxelPos::new(10, 5, 5), "glass");
+        set(&mut world, &registry, VoxelPos::new(15, 5, 5), "air");
+        assert_eq!(light(&world, 10, 5, 5), 14);
+        assert_eq!(light(&world, 15, 5, 5), 9);
+        assert_eq!(light(&world, 18, 5, 5), 6);
+    }
+
+    #[test]
+    fn relight_removes_old_light() {
+        let (mut world, registry) = world();
+        assert_eq!(light(&world, 16, 5, 5), 13);
+
+        // Swap the emitter out without updating then relight the whole chunk
+        world.set_voxel(VoxelPos::new(15, 5, 5), block("air"));
+        propagate_block_light(&mut world, &registry, ChunkPos::new(0, 0, 0));
+        for x in 0..24 {
+            assert_eq!(light(&world, x, 5, 5), 0);
+        }
+        assert_eq!(light(&world, 15, -1, 5), 0);
+    }
+}
@@ -0,0 +1,132 @@
+use super::{
+    block::{relight_chunk, relight_voxel},
+    LightChannel,
+};
+use crate::prelude::*;
+
+/// Level of one color of a voxels emitted light. The emission level is scaled by that color of `LightEmission::color`
+pub fn emitted_color<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    voxel: &V,
+    registry: &R,
+    color: usize,
+) -> u8 {
+    let emission = voxel.emission(Some(registry));
+    let half = MAX_LIGHT as u16 / 2;
+    ((emission.level as u16 * emission.color[color] as u16 + half) / MAX_LIGHT as u16) as u8
+}
+
+/// Colored version of `propagate_block_light`. Red, green and blue light are each flooded on their own and are
+/// tinted by the light filter of every voxel they pass through. Stored separately from the single channel block light
+pub fn propagate_colored_light<
+    V: Voxel<R> + Clone + Eq + Default,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &mut ChunkMap<V, R>,
+    registry: &R,
+    chunk_pos: ChunkPos,
+) {
+    for (color, channel) in LightChannel::COLORS.into_iter().enumerate() {
+        relight_chunk(world, registry, chunk_pos, channel, |voxel| {
+            emitted_color(voxel, registry, color)
+        });
+    }
+}
+
+/// Colored version of `update_block_light`. Call after the voxel at pos was changed
+pub fn update_colored_light<
+    V: Voxel<R> + Clone + Eq + Default,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &mut ChunkMap<V, R>,
+    registry: &R,
+    pos: VoxelPos,
+) {
+    for (color, channel) in LightChannel::COLORS.into_iter().enumerate() {
+        relight_voxel(world, registry, pos, channel, |voxel| {
+            emitted_color(voxel, registry, color)
+        });
+    }
+}
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::{light::get_light, test_util::*};
+
+    fn colors(world: &ChunkMap<BlockData, BlockRegistry>, x: i32, y: i32, z: i32) -> [u8; 3] {
+        LightChannel::COLORS
+            .map(|channel| get_light(world, VoxelPos::new(x, y, z), channel).unwrap())
+    }
+
+    // An orange lamp at 5, 5, 5 behind a red glass wall at x 8
+    fn lit_world() -> (ChunkMap<BlockData, BlockRegistry>, BlockRegistry) {
+        let (mut registry, _, _) = registries();
+        let lamp = registry.get_mut("vinox:slab").unwrap();
+        lamp.emission = Some(14);
+        lamp.emission_color = Some([15, 8, 0]);
+        registry.get_mut("vinox:glass").unwrap().light_filter = Some([15, 0, 0]);
+
+        let mut world = ChunkMap::default();
+        let center = ChunkPos::new(0, 0, 0);
+        for pos in center.neighbors().iter().chain([center].iter()) {
+            world.insert(*pos, ChunkData::default());
+        }
+        world.set_voxel(VoxelPos::new(5, 5, 5), block("slab"));
+        for y in -16..32 {
+            for z in -16..32 {
+                world.set_voxel(VoxelPos::new(8, y, z), block("glass"));
+            }
+        }
+        for pos in center.neighbors().iter().chain([center].iter()) {
+            propagate_colored_light(&mut world, &registry, *pos);
+        }
+        (world, registry)
+    }
+
+    #[test]
+    fn filtered_colors() {
+        let (mut world, registry) = lit_world();
+        assert_eq!(colors(&world, 5, 5, 5), [14, 7, 0]);
+        assert_eq!(colors(&world, 7, 5, 5), [12, 5, 0]);
+        assert_eq!(colors(&world, 8, 5, 5), [11, 0, 0]);
+        assert_eq!(colors(&world, 9, 5, 5), [10, 0, 0]);
+        assert_eq!(colors(&world, 5, 5, 9), [10, 3, 0]);
+
+        world.set_voxel(VoxelPos::new(5, 5, 5), block("air"));
+        update_colored_light(&mut world, &registry, VoxelPos::new(5, 5, 5));
+        assert_eq!(colors(&world, 7, 5, 5), [0, 0, 0]);
+        assert_eq!(colors(&world, 9, 5, 5), [0, 0, 0]);
+
+        world.set_voxel(VoxelPos::new(5, 5, 5), block("slab"));
+        update_colored_light(&mut world, &registry, VoxelPos::new(5, 5, 5));
+        assert_eq!(colors(&world, 9, 5, 5), [10, 0, 0]);
+    }
+
+    #[test]
+    fn colored_mesh() {
+        let (world, vox_registry) = lit_world();
+        let (_, geo_registry, asset_registry) = registries();
+        let pos = ChunkPos::new(0, 0, 0);
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            world[&pos].clone(),
+            world.neighbors(pos).unwrap(),
+            pos,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        let mesh = full_mesh_with_options(
+            &asset_registry,
+            &boundary,
+            *pos,
+            &MeshOptions {
+                colored_light: true,
+                ..Default::default()
+            },
+        );
+        let colors = mesh.transparent_mesh.colors.unwrap();
+        assert!(colors
+            .iter()
+            .any(|color| color[0] > color[1] && color[1] > color[2]));
+    }
+}
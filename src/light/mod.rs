@@ -0,0 +1,215 @@
+pub mod block;
+pub mod colored;
+pub mod sky;
+
+use std::collections::VecDeque;
+
+use crate::prelude::*;
+
+/// The six face neighbors of a voxel in the same order as the sides of a block. West, East, Down, Up, South, North
+pub(crate) const NEIGHBOR_OFFSETS: [(i32, i32, i32); 6] = [
+    (-1, 0, 0),
+    (1, 0, 0),
+    (0, -1, 0),
+    (0, 1, 0),
+    (0, 0, -1),
+    (0, 0, 1),
+];
+
+/// Which light a propagation works on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LightChannel {
+    /// Light given off by blocks such as torches
+    Block,
+    /// Light coming from the sky. Kept separate from block light so it can be scaled for day and night
+    Sky,
+    /// The colors of colored block light. Each one propagates on its own
+    Red,
+    Green,
+    Blue,
+}
+
+impl LightChannel {
+    pub const COLORS: [LightChannel; 3] =
+        [LightChannel::Red, LightChannel::Green, LightChannel::Blue];
+
+    /// Index of the color in an RGB array or None for the uncolored channels
+    pub fn color_index(&self) -> Option<usize> {
+        match self {
+            LightChannel::Red => Some(0),
+            LightChannel::Green => Some(1),
+            LightChannel::Blue => Some(2),
+            LightChannel::Block | LightChannel::Sky => None,
+        }
+    }
+}
+
+/// Whether light can pass through a voxel. Voxels that are not opaque or that are truly empty let light through
+pub fn transmits_light<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    voxel: &V,
+    registry: &R,
+) -> bool {
+    !voxel.is_opaque(Some(registry)) || voxel.is_true_empty(Some(registry))
+}
+
+pub(crate) fn offset(pos: VoxelPos, offset: (i32, i32, i32)) -> VoxelPos {
+    VoxelPos::new(pos.x + offset.0, pos.y + offset.1, pos.z + offset.2)
+}
+
+/// Every voxel just outside the faces of a chunk
+pub(crate) fn border_voxels(chunk_pos: ChunkPos) -> impl Iterator<Item = VoxelPos> {
+    let edge = CHUNK_SIZE as i32;
+    let origin = VoxelPos::from((RelativeVoxelPos::new(0, 0, 0), chunk_pos));
+    (0..edge).flat_map(move |a| {
+        (0..edge).flat_map(move |b| {
+            [
+                VoxelPos::new(origin.x - 1, origin.y + a, origin.z + b),
+                VoxelPos::new(origin.x + edge, origin.y + a, origin.z + b),
+                VoxelPos::new(origin.x + a, origin.y - 1, origin.z + b),
+                VoxelPos::new(origin.x + a, origin.y + edge, origin.z + b),
+                VoxelPos::new(origin.x + a, origin.y + b, origin.z - 1),
+                VoxelPos::new(origin.x + a, origin.y + b, origin.z + edge),
+            ]
+        })
+    })
+}
+
+/// Light level at a world position or None if its chunk isn't loaded
+pub fn get_light<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    world: &ChunkMap<V, R>,
+    pos: VoxelPos,
+    channel: LightChannel,
+) -> Option<u8> {
+    let (relative, chunk_pos) = pos.to_offsets();
+    let chunk = world.get(&chunk_pos)?;
+    Some(match channel {
+        LightChannel::Block => chunk.get_block_light(relative),
+        LightChannel::Sky => chunk.get_sky_light(relative),
+        LightChannel::Red | LightChannel::Green | LightChannel::Blue => {
+            chunk.get_colored_light(relative)[channel.color_index().unwrap_or_default()]
+        }
+    })
+}
+
+pub(crate) fn set_light<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    world: &mut ChunkMap<V, R>,
+    pos: VoxelPos,
+    channel: LightChannel,
+    level: u8,
+) {
+    let (relative, chunk_pos) = pos.to_offsets();
+    if let Some(chunk) = world.get_mut(&chunk_pos) {
+        match channel {
+            LightChannel::Block => chunk.set_block_light(relative, level),
+            LightChannel::Sky => chunk.set_sky_light(relative, level),
+            LightChannel::Red | LightChannel::Green | LightChannel::Blue => {
+                chunk.set_colored_light(relative, channel.color_index().unwrap_or_default(), level)
+            }
+        }
+    }
+}
+
+/// The level light has after spreading from a voxel with the given level to its neighbor.
+/// Full sky light travels straight down without getting dimmer
+fn spread_level(channel: LightChannel, level: u8, neighbor: usize) -> u8 {
+    if channel == LightChannel::Sky && neighbor == 2 && level == MAX_LIGHT {
+        MAX_LIGHT
+    } else {
+        level.saturating_sub(1)
+    }
+}
+
+/// Colored light entering a voxel is tinted by its light filter
+fn filter_level<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    channel: LightChannel,
+    level: u8,
+    voxel: &V,
+    registry: &R,
+) -> u8 {
+    match channel.color_index() {
+        Some(color) => {
+            let filter = voxel.light_filter(Some(registry))[color];
+            (level as u16 * filter as u16 / MAX_LIGHT as u16) as u8
+        }
+        None => level,
+    }
+}
+
+/// Flood fills light outwards from every queued voxel. Queued voxels must already have their light set
+pub(crate) fn propagate<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    world: &mut ChunkMap<V, R>,
+    registry: &R,
+    channel: LightChannel,
+    mut queue: VecDeque<VoxelPos>,
+) {
+    while let Some(pos) = queue.pop_front() {
+        let Some(level) = get_light(world, pos, channel) else {
+            continue;
+        };
+        for (neighbor_num, neighbor_offset) in NEIGHBOR_OFFSETS.iter().enumerate() {
+            let new_level = spread_level(channel, level, neighbor_num);
+            if new_level == 0 {
+                continue;
+            }
+            let neighbor = offset(pos, *neighbor_offset);
+            let Some(neighbor_level) = get_light(world, neighbor, channel) else {
+                continue;
+            };
+            if neighbor_level >= new_level {
+                continue;
+            }
+            let Some(voxel) = world.get_voxel(neighbor) else {
+                continue;
+            };
+            if !transmits_light(&voxel, registry) {
+                continue;
+            }
+            let new_level = filter_level(channel, new_level, &voxel, registry);
+            if neighbor_level < new_level {
+                set_light(world, neighbor, channel, new_level);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+}
+
+/// Removes light that came from the queued voxels (given with the level they used to have). Voxels that are lit
+/// by something else are returned so their light can be spread back into the darkened area.
+/// Emitters that were darkened get their own light back and are returned as well
+pub(crate) fn unpropagate<
+    V: Voxel<R> + Clone + Eq + Default,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &mut ChunkMap<V, R>,
+    channel: LightChannel,
+    mut queue: VecDeque<(VoxelPos, u8)>,
+    emission: &impl Fn(&V) -> u8,
+) -> VecDeque<VoxelPos> {
+    let mut relight = VecDeque::new();
+    while let Some((pos, level)) = queue.pop_front() {
+        for (neighbor_num, neighbor_offset) in NEIGHBOR_OFFSETS.iter().enumerate() {
+            let neighbor = offset(pos, *neighbor_offset);
+            let Some(neighbor_level) = get_light(world, neighbor, channel) else {
+                continue;
+            };
+            if neighbor_level == 0 {
+                continue;
+            }
+            if neighbor_level <= spread_level(channel, level, neighbor_num) {
+                set_light(world, neighbor, channel, 0);
+                queue.push_back((neighbor, neighbor_level));
+                let emitted = world
+                    .get_voxel(neighbor)
+                    .map(|voxel| emission(&voxel))
+                    .unwrap_or_default();
+                if emitted > 0 {
+                    set_light(world, neighbor, channel, emitted);
+                    relight.push_back(neighbor);
+                }
+            } else {
+                relight.push_back(neighbor);
+            }
+        }
+    }
+    relight
+}
@@ -0,0 +1,405 @@
+use std::collections::VecDeque;
+
+use ahash::HashMap;
+use derive_more::{Deref, DerefMut};
+
+use super::{
+    border_voxels, get_light, offset, propagate, set_light, transmits_light, unpropagate,
+    LightChannel, NEIGHBOR_OFFSETS,
+};
+use crate::prelude::*;
+
+/// The world y of the highest voxel that blocks light in every (x, z) column of a stack of chunks.
+/// None means nothing in the loaded chunks of that column blocks light
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heightmap {
+    heights: Vec<Option<i32>>,
+}
+
+impl Default for Heightmap {
+    fn default() -> Self {
+        Self {
+            heights: vec![None; CHUNK_SIZE * CHUNK_SIZE],
+        }
+    }
+}
+
+impl Heightmap {
+    /// x and z are relative to the chunk column
+    pub fn get(&self, x: usize, z: usize) -> Option<i32> {
+        self.heights[z * CHUNK_SIZE + x]
+    }
+
+    pub fn set(&mut self, x: usize, z: usize, height: Option<i32>) {
+        self.heights[z * CHUNK_SIZE + x] = height;
+    }
+
+    /// The highest blocking voxel of the whole column
+    pub fn max(&self) -> Option<i32> {
+        self.heights.iter().flatten().max().copied()
+    }
+}
+
+/// Heightmaps of every chunk column keyed by the chunks x and z
+#[derive(Deref, DerefMut, Default, Clone, Debug)]
+pub struct Heightmaps(pub HashMap<[i32; 2], Heightmap>);
+
+/// The chunk positions loaded in a column from the top down
+fn column_chunks<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    world: &ChunkMap<V, R>,
+    column: [i32; 2],
+) -> Vec<ChunkPos> {
+    let mut chunks = world
+        .keys()
+        .filter(|pos| pos.x == column[0] && pos.z == column[1])
+        .copied()
+        .collect::<Vec<_>>();
+    chunks.sort_by_key(|pos| std::cmp::Reverse(pos.y));
+    chunks
+}
+
+/// Scans a single column of voxels down from y for the first voxel that blocks light. Stops at unloaded chunks
+fn scan_height<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    world: &ChunkMap<V, R>,
+    registry: &R,
+    x: i32,
+    mut y: i32,
+    z: i32,
+) -> Option<i32> {
+    while let Some(voxel) = world.get_voxel(VoxelPos::new(x, y, z)) {
+        if !transmits_light(&voxel, registry) {
+            return Some(y);
+        }
+        y -= 1;
+    }
+    None
+}
+
+/// Finds the highest light blocking voxel of every (x, z) column across all loaded chunks of a chunk column
+pub fn compute_heightmap<
+    V: Voxel<R> + Clone + Eq + Default,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &ChunkMap<V, R>,
+    registry: &R,
+    column: [i32; 2],
+) -> Heightmap {
+    let mut heightmap = Heightmap::default();
+    for chunk_pos in column_chunks(world, column) {
+        let Some(chunk) = world.get(&chunk_pos) else {
+            continue;
+        };
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                if heightmap.get(x, z).is_some() {
+                    continue;
+                }
+                let height = (0..CHUNK_SIZE).rev().find(|y| {
+                    !transmits_light(
+                        &chunk.get(RelativeVoxelPos::new(x as u32, *y as u32, z as u32)),
+                        registry,
+                    )
+                });
+                if let Some(y) = height {
+                    let world_y = VoxelPos::from((
+                        RelativeVoxelPos::new(x as u32, y as u32, z as u32),
+                        chunk_pos,
+                    ))
+                    .y;
+                    heightmap.set(x, z, Some(world_y));
+                }
+            }
+        }
+    }
+    heightmap
+}
+
+/// Lights a whole chunk column from scratch. Every voxel above the heightmap gets full sky light which then spreads
+/// sideways into caves and under overhangs, including into loaded neighboring columns. Light from the neighboring
+/// columns is pulled back in as well. Anything above the highest loaded chunk is treated as open sky
+pub fn propagate_sky_light<
+    V: Voxel<R> + Clone + Eq + Default,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &mut ChunkMap<V, R>,
+    heightmaps: &mut Heightmaps,
+    registry: &R,
+    column: [i32; 2],
+) {
+    let heightmap = compute_heightmap(world, registry, column);
+    let column_max = heightmap.max();
+    let chunks = column_chunks(world, column);
+
+    let mut queue = VecDeque::new();
+    for chunk_pos in &chunks {
+        let Some(chunk) = world.get_mut(chunk_pos) else {
+            continue;
+        };
+        let origin = VoxelPos::from((RelativeVoxelPos::new(0, 0, 0), *chunk_pos));
+        chunk.set_lit(true);
+        chunk.set_dirty(true);
+        if column_max.is_none_or(|max| origin.y > max) {
+            // Nothing in the chunk is shadowed so light only spreads out of its sides into neighboring columns
+            // that may be shadowed at this height. Otherwise the result would depend on which column was lit first
+            chunk.sky_light_mut().fill(MAX_LIGHT);
+            for (direction_x, direction_z) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let neighbor_column = [column[0] + direction_x, column[1] + direction_z];
+                let neighbor_pos =
+                    ChunkPos::new(neighbor_column[0], chunk_pos.y, neighbor_column[1]);
+                let shadowed = heightmaps
+                    .get(&neighbor_column)
+                    .is_none_or(|heightmap| heightmap.max().is_some_and(|max| origin.y <= max));
+                if shadowed && world.contains_key(&neighbor_pos) {
+                    queue.extend(side_voxels(*chunk_pos, direction_x, direction_z));
+                }
+            }
+            continue;
+        }
+        chunk.sky_light_mut().fill(0);
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                let height = heightmap.get(x, z);
+                // Only voxels next to a shadowed voxel need to spread light sideways
+                let mut spread_below = height;
+                for (neighbor_x, neighbor_z) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                    let (neighbor_x, neighbor_z) = (x as i32 + neighbor_x, z as i32 + neighbor_z);
+                    let neighbor_height = if (0..CHUNK_SIZE as i32).contains(&neighbor_x)
+                        && (0..CHUNK_SIZE as i32).contains(&neighbor_z)
+                    {
+                        heightmap.get(neighbor_x as usize, neighbor_z as usize)
+                    } else {
+                        // Unknown neighboring column so any height could be shadowed
+                        Some(i32::MAX)
+                    };
+                    spread_below = spread_below.max(neighbor_height);
+                }
+                for y in 0..CHUNK_SIZE {
+                    let world_y = origin.y + y as i32;
+                    if height.is_some_and(|height| world_y <= height) {
+                        continue;
+                    }
+                    let relative = RelativeVoxelPos::new(x as u32, y as u32, z as u32);
+                    chunk.set_sky_light(relative, MAX_LIGHT);
+                    if spread_below.is_some_and(|below| world_y <= below) {
+                        queue.push_back(VoxelPos::from((relative, *chunk_pos)));
+                    }
+                }
+            }
+        }
+    }
+
+    // Lit voxels in the neighboring columns spread back in
+    for chunk_pos in &chunks {
+        queue.extend(border_voxels(*chunk_pos).filter(|pos| {
+            let (_, border_chunk) = pos.to_offsets();
+            border_chunk.x != column[0] || border_chunk.z != column[1]
+        }));
+    }
+    queue.retain(|pos| get_light(world, *pos, LightChannel::Sky).is_some_and(|level| level > 1));
+
+    heightmaps.insert(column, heightmap);
+    propagate(world, registry, LightChannel::Sky, queue);
+}
+
+/// The voxels of a chunk along its -x, +x, -z or +z side
+fn side_voxels(
+    chunk_pos: ChunkPos,
+    direction_x: i32,
+    direction_z: i32,
+) -> impl Iterator<Item = VoxelPos> {
+    let edge = CHUNK_SIZE as i32;
+    let origin = VoxelPos::from((RelativeVoxelPos::new(0, 0, 0), chunk_pos));
+    let side = |direction: i32| if direction > 0 { edge - 1 } else { 0 };
+    let (side_x, side_z) = (side(direction_x), side(direction_z));
+    (0..edge).flat_map(move |a| {
+        (0..edge).map(move |y| {
+            if direction_x != 0 {
+                VoxelPos::new(origin.x + side_x, origin.y + y, origin.z + a)
+            } else {
+                VoxelPos::new(origin.x + a, origin.y + y, origin.z + side_z)
+            }
+        })
+    })
+}
+
+/// Call after the voxel at pos was placed or broken. Updates the heightmap of its column, removes the sky light the
+/// old voxel let through and lets light back in from above and around it
+pub fn update_sky_light<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    world: &mut ChunkMap<V, R>,
+    heightmaps: &mut Heightmaps,
+    registry: &R,
+    pos: VoxelPos,
+) {
+    let Some(voxel) = world.get_voxel(pos) else {
+        return;
+    };
+    let (relative, chunk_pos) = pos.to_offsets();
+    let column = [chunk_pos.x, chunk_pos.z];
+    let heightmap = heightmaps
+        .0
+        .entry(column)
+        .or_insert_with(|| compute_heightmap(world, registry, column));
+
+    let (x, z) = (relative.x as usize, relative.z as usize);
+    let transmits = transmits_light(&voxel, registry);
+    let height = heightmap.get(x, z);
+    if !transmits && height.is_none_or(|height| pos.y > height) {
+        heightmap.set(x, z, Some(pos.y));
+    } else if transmits && height == Some(pos.y) {
+        heightmap.set(x, z, scan_height(world, registry, pos.x, pos.y - 1, pos.z));
+    }
+    let height = heightmap.get(x, z);
+
+    let old_level = get_light(world, pos, LightChannel::Sky).unwrap_or_default();
+    set_light(world, pos, LightChannel::Sky, 0);
+    let mut relight = unpropagate(
+        world,
+        LightChannel::Sky,
+        VecDeque::from([(pos, old_level)]),
+        &|_| 0,
+    );
+
+    if transmits {
+        if height.is_none_or(|height| pos.y > height) {
+            set_light(world, pos, LightChannel::Sky, MAX_LIGHT);
+            relight.push_back(pos);
+        }
+        // Let the surrounding light back in
+        for neighbor_offset in NEIGHBOR_OFFSETS {
+            let neighbor = offset(pos, neighbor_offset);
+            if get_light(world, neighbor, LightChannel::Sky).is_some_and(|level| level > 1) {
+                relight.push_back(neighbor);
+            }
+        }
+    }
+
+    propagate(world, registry, LightChannel::Sky, relight);
+}
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn empty_world(columns: &[[i32; 2]], heights: &[i32]) -> ChunkMap<BlockData, BlockRegistry> {
+        let mut world = ChunkMap::default();
+        for column in columns {
+            for y in heights {
+                world.insert(
+                    ChunkPos::new(column[0], *y, column[1]),
+                    ChunkData::default(),
+                );
+            }
+        }
+        world
+    }
+
+    fn light(world: &ChunkMap<BlockData, BlockRegistry>, x: i32, y: i32, z: i32) -> u8 {
+        get_light(world, VoxelPos::new(x, y, z), LightChannel::Sky).unwrap()
+    }
+
+    fn columns() -> Vec<[i32; 2]> {
+        (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |z| [x, z]))
+            .collect()
+    }
+
+    #[test]
+    fn shadow_and_updates() {
+        let (registry, _, _) = registries();
+        let mut world = empty_world(&columns(), &[-1, 0, 1]);
+        for x in 0..8 {
+            for z in 0..8 {
+                world.set_voxel(VoxelPos::new(x, 10, z), block("stone"));
+            }
+        }
+        let mut heightmaps = Heightmaps::default();
+        for column in columns() {
+            propagate_sky_light(&mut world, &mut heightmaps, &registry, column);
+        }
+        assert_eq!(heightmaps[&[0, 0]].get(4, 4), Some(10));
+        assert_eq!(light(&world, 4, 20, 4), MAX_LIGHT);
+        assert_eq!(light(&world, 4, 10, 4), 0);
+        assert_eq!(light(&world, 4, 5, 4), 11);
+        assert_eq!(light(&world, 4, -10, 4), 11);
+        assert_eq!(light(&world, 8, -10, 4), MAX_LIGHT);
+
+        // Opening the roof lets full light straight down
+        world.set_voxel(VoxelPos::new(4, 10, 4), block("air"));
+        update_sky_light(
+            &mut world,
+            &mut heightmaps,
+            &registry,
+            VoxelPos::new(4, 10, 4),
+        );
+        assert_eq!(heightmaps[&[0, 0]].get(4, 4), None);
+        assert_eq!(light(&world, 4, 5, 4), MAX_LIGHT);
+        assert_eq!(light(&world, 3, 5, 4), 14);
+
+        world.set_voxel(VoxelPos::new(4, 10, 4), block("stone"));
+        update_sky_light(
+            &mut world,
+            &mut heightmaps,
+            &registry,
+            VoxelPos::new(4, 10, 4),
+        );
+        assert_eq!(light(&world, 4, 5, 4), 11);
+        assert_eq!(light(&world, 3, 5, 4), 11);
+
+        world.set_voxel(VoxelPos::new(20, 5, 20), block("stone"));
+        update_sky_light(
+            &mut world,
+            &mut heightmaps,
+            &registry,
+            VoxelPos::new(20, 5, 20),
+        );
+        assert_eq!(light(&world, 20, 6, 20), MAX_LIGHT);
+        assert_eq!(light(&world, 20, 4, 20), 14);
+        assert_eq!(light(&world, 20, -16, 20), 14);
+
+        // Relighting from scratch matches the incremental updates
+        let mut relit = world.clone();
+        let mut relit_heightmaps = Heightmaps::default();
+        for column in columns() {
+            propagate_sky_light(&mut relit, &mut relit_heightmaps, &registry, column);
+        }
+        for x in -16..32 {
+            for y in -16..32 {
+                for z in -16..32 {
+                    assert_eq!(light(&world, x, y, z), light(&relit, x, y, z));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn column_order_does_not_matter() {
+        let (registry, _, _) = registries();
+        // An open column next to one roofed over at y 20. The open column is above its (missing) heightmap everywhere
+        let mut world = empty_world(&[[0, 0], [1, 0]], &[0, 1]);
+        for x in 16..32 {
+            for z in 0..16 {
+                world.set_voxel(VoxelPos::new(x, 20, z), block("stone"));
+            }
+        }
+        let lit = |order: [[i32; 2]; 2]| {
+            let mut world = world.clone();
+            let mut heightmaps = Heightmaps::default();
+            for column in order {
+                propagate_sky_light(&mut world, &mut heightmaps, &registry, column);
+            }
+            world
+        };
+        let open_first = lit([[0, 0], [1, 0]]);
+        let roofed_first = lit([[1, 0], [0, 0]]);
+        assert_eq!(light(&roofed_first, 16, 19, 5), 14);
+        assert_eq!(light(&roofed_first, 20, 10, 5), 10);
+        for x in 0..32 {
+            for y in 0..32 {
+                for z in 0..16 {
+                    assert_eq!(light(&open_first, x, y, z), light(&roofed_first, x, y, z));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    cell::RefCell,
+    cmp::Reverse,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use serde::Serialize;
+
+use crate::prelude::*;
+
+thread_local! {
+    // Every worker thread reuses its own quad, AO and light scratch between the chunks it meshes
+    static BUFFERS: RefCell<MeshBuffers> = RefCell::new(MeshBuffers::default());
+}
+
+/// A chunk to be meshed by `mesh_batch`. Jobs with a higher priority are meshed and returned first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeshJob {
+    pub pos: ChunkPos,
+    pub priority: i32,
+}
+
+impl MeshJob {
+    pub fn new(pos: ChunkPos, priority: i32) -> Self {
+        Self { pos, priority }
+    }
+}
+
+/// A finished `mesh_batch` job. The mesh is the panic payload as an Err if meshing the chunk panicked
+pub struct MeshBatchResult {
+    pub job: MeshJob,
+    pub mesh: std::thread::Result<MeshedChunk>,
+    // Position of the job in the queue, keeps jobs with the same priority in order when sorting
+    order: usize,
+}
+
+/// Queues meshing many chunks of a world on a worker pool and returns without waiting for them. Jobs are queued
+/// highest priority first (jobs with the same priority keep their order) so workers pick them up in that order.
+/// Results stream back through `WorkerPool::try_recv` or `WorkerPool::recv` as soon as each one finishes so they
+/// can arrive out of priority order, use `mesh_batch_blocking` to get them sorted.
+/// The center chunk and its neighbors are copied when the job is queued. Jobs for chunks that aren't loaded are
+/// skipped and missing neighbors are treated as empty. Each worker reuses its own `MeshBuffers` between chunks,
+/// the vertex buffers of every mesh are new since they are handed back
+pub fn mesh_batch<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R> + Send + 'static,
+    R: VoxRegistry<V> + Clone + Default + Send + Sync + 'static,
+>(
+    pool: &mut WorkerPool<MeshBatchResult>,
+    world: &ChunkMap<V, R>,
+    jobs: &[MeshJob],
+    vox_registry: &Arc<R>,
+    geo_registry: &Arc<GeometryRegistry>,
+    asset_registry: &Arc<AssetRegistry>,
+    options: &MeshOptions,
+) {
+    let mut jobs = jobs.to_vec();
+    jobs.sort_by_key(|job| Reverse(job.priority));
+
+    for (order, job) in jobs.into_iter().enumerate() {
+        let Some(chunk) = world.get(&job.pos).cloned() else {
+            continue;
+        };
+        let neighbors = world.neighbors_or_default(job.pos);
+        let vox_registry = vox_registry.clone();
+        let geo_registry = geo_registry.clone();
+        let asset_registry = asset_registry.clone();
+        let options = *options;
+        pool.spawn(move || {
+            // Caught here instead of by the pool so a failed mesh still says which chunk it was for
+            let mesh = catch_unwind(AssertUnwindSafe(|| {
+                let boundary = ChunkBoundary::<V, R>::new_at(
+                    chunk,
+                    neighbors,
+                    job.pos,
+                    &vox_registry,
+                    &geo_registry,
+                    &asset_registry,
+                );
+                BUFFERS.with_borrow_mut(|buffers| {
+                    full_mesh_with_buffers(&asset_registry, &boundary, *job.pos, &options, buffers)
+                })
+            }));
+            MeshBatchResult { job, mesh, order }
+        });
+    }
+}
+
+/// Same as `mesh_batch` but waits for every job in the pool to finish and returns the results highest priority
+/// first, jobs with the same priority keep their order
+pub fn mesh_batch_blocking<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R> + Send + 'static,
+    R: VoxRegistry<V> + Clone + Default + Send + Sync + 'static,
+>(
+    pool: &mut WorkerPool<MeshBatchResult>,
+    world: &ChunkMap<V, R>,
+    jobs: &[MeshJob],
+    vox_registry: &Arc<R>,
+    geo_registry: &Arc<GeometryRegistry>,
+    asset_registry: &Arc<AssetRegistry>,
+    options: &MeshOptions,
+) -> Vec<MeshBatchResult> {
+    mesh_batch(
+        pool,
+        world,
+        jobs,
+        vox_registry,
+        geo_registry,
+        asset_registry,
+        options,
+    );
+    let mut results = Vec::with_capacity(pool.pending());
+    while let Some(result) = pool.recv() {
+        // Jobs catch their own panics so the pool never hands back an Err
+        results.extend(result.ok());
+    }
+    results.sort_by_key(|result| (Reverse(result.job.priority), result.order));
+    results
+}
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn world() -> ChunkMap<BlockData, BlockRegistry> {
+        let mut world = ChunkMap::default();
+        for x in -1..=1 {
+            for y in -1..=0 {
+                for z in -1..=1 {
+                    world.insert(ChunkPos::new(x, y, z), ChunkData::default());
+                }
+            }
+        }
+        let mut seed = 1u64;
+        for x in -16..32 {
+            for y in -8..8 {
+                for z in -16..32 {
+                    seed = seed
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    if (seed >> 33).is_multiple_of(3) {
+                        world.set_voxel(VoxelPos::new(x, y, z), block("stone"));
+                    }
+                }
+            }
+        }
+        world
+    }
+
+    fn batch(workers: usize) {
+        let (vox_registry, geo_registry, asset_registry) = registries();
+        let world = world();
+        let mut jobs = world
+            .keys()
+            .enumerate()
+            .map(|(index, pos)| MeshJob::new(*pos, (index % 4) as i32))
+            .collect::<Vec<_>>();
+        // Not loaded so skipped
+        jobs.push(MeshJob::new(ChunkPos::new(5, 5, 5), 10));
+
+        let mut pool = WorkerPool::new(workers);
+        let (vox_registry, geo_registry, asset_registry) = (
+            Arc::new(vox_registry),
+            Arc::new(geo_registry),
+            Arc::new(asset_registry),
+        );
+        let results = mesh_batch_blocking(
+            &mut pool,
+            &world,
+            &jobs,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+            &MeshOptions::default(),
+        );
+        assert_eq!(pool.pending(), 0);
+        assert_eq!(results.len(), world.len());
+        // Highest priority first and in job order within a priority, however the workers finished
+        let mut expected = jobs
+            .iter()
+            .filter(|job| world.contains_key(&job.pos))
+            .copied()
+            .collect::<Vec<_>>();
+        expected.sort_by_key(|job| Reverse(job.priority));
+        assert_eq!(
+            results.iter().map(|result| result.job).collect::<Vec<_>>(),
+            expected
+        );
+
+        let meshes = results
+            .into_iter()
+            .map(|result| {
+                let mesh = result.mesh.unwrap();
+                assert_eq!(mesh.pos, result.job.pos);
+                mesh
+            })
+            .collect::<Vec<_>>();
+        for mesh in meshes {
+            let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+                world[&mesh.pos].clone(),
+                world.neighbors_or_default(mesh.pos),
+                mesh.pos,
+                &vox_registry,
+                &geo_registry,
+                &asset_registry,
+            );
+            let single = full_mesh(&asset_registry, &boundary, *mesh.pos);
+            assert_eq!(single.chunk_mesh.vertices, mesh.chunk_mesh.vertices);
+            assert_eq!(single.chunk_mesh.colors, mesh.chunk_mesh.colors);
+        }
+    }
+
+    #[test]
+    fn batch_inline() {
+        batch(0);
+    }
+
+    #[test]
+    fn batch_threaded() {
+        batch(2);
+    }
+}
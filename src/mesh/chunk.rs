@@ -24,7 +24,6 @@ pub struct BlockMatches {
 /// 03: Up
 /// 04: South
 /// 05: North
-
 pub trait RenderedVoxel<
     V: Voxel<R> + Clone + Serialize + Eq + Default,
     R: VoxRegistry<V> + Clone + Default,
@@ -36,6 +35,16 @@ pub trait RenderedVoxel<
         geo_registry: Option<&GeometryRegistry>,
         vox_registry: Option<&R>,
     ) -> Option<usize>;
+    /// `to_geo_idx` for one of the `variant_weights`. None is the voxel itself
+    fn to_geo_idx_variant(
+        &self,
+        geo_pal: Option<&mut GeoPalette>,
+        geo_registry: Option<&GeometryRegistry>,
+        vox_registry: Option<&R>,
+        _variant: Option<usize>,
+    ) -> Option<usize> {
+        self.to_geo_idx(geo_pal, geo_registry, vox_registry)
+    }
 
     fn to_match_idx(&self, match_pal: Option<&mut BlockMatches>) -> usize;
     /// These should return the uvs for the whole texture of this face this doesn't include the uvs for geometry faces
@@ -44,14 +53,59 @@ pub trait RenderedVoxel<
         vox_registry: Option<&R>,
         asset_registry: Option<&AssetRegistry>,
     ) -> Option<[UVRect; 6]>;
+    /// `to_texture_uv` for one of the `variant_weights`. None is the voxel itself
+    fn to_texture_uv_variant(
+        &self,
+        vox_registry: Option<&R>,
+        asset_registry: Option<&AssetRegistry>,
+        _variant: Option<usize>,
+    ) -> Option<[UVRect; 6]> {
+        self.to_texture_uv(vox_registry, asset_registry)
+    }
+    /// Texture array layer of every face for `TextureMode::Layers`. Same as `to_texture_uv` otherwise
+    fn to_texture_layers(
+        &self,
+        _vox_registry: Option<&R>,
+        _asset_registry: Option<&AssetRegistry>,
+        _variant: Option<usize>,
+    ) -> Option<[u32; 6]> {
+        None
+    }
     /// Returns if a side of this voxel will block that faces neighbor
     fn blocking_sides(
         &self,
         vox_registry: Option<&R>,
         geo_registry: Option<&GeometryRegistry>,
     ) -> Option<([bool; 6], Option<[bool; 6]>)>;
+    /// `blocking_sides` for one of the `variant_weights`. None is the voxel itself
+    fn blocking_sides_variant(
+        &self,
+        vox_registry: Option<&R>,
+        geo_registry: Option<&GeometryRegistry>,
+        _variant: Option<usize>,
+    ) -> Option<([bool; 6], Option<[bool; 6]>)> {
+        self.blocking_sides(vox_registry, geo_registry)
+    }
+    /// Weights of the looks this voxel can pick from by position. The index of the picked one is passed as variant
+    /// to `to_geo_idx_variant`, `to_texture_uv_variant`, `blocking_sides_variant` and `to_texture_layers`. Empty
+    /// when the voxel always looks the same
+    fn variant_weights(&self, _vox_registry: Option<&R>) -> Vec<u32> {
+        vec![]
+    }
 
-    fn light_level() -> Option<u8>;
+    /// Old way to say how much light a voxel gives off. Lighting reads `Voxel::emission` now and never calls this
+    #[deprecated(note = "use Voxel::emission")]
+    fn light_level() -> Option<u8> {
+        None
+    }
+    /// Whether this voxel is part of the smooth surface from `smooth_mesh` instead of being meshed as a block
+    fn is_smooth(&self, _vox_registry: Option<&R>) -> bool {
+        false
+    }
+    /// How this voxels faces pick their tile from neighbors or None if its textures are single tiles
+    fn connected_textures(&self, _vox_registry: Option<&R>) -> Option<ConnectedTextures> {
+        None
+    }
     fn to_visibility(
         &self,
         vox_registry: Option<&R>,
@@ -78,352 +132,117 @@ impl<
         R: VoxRegistry<V> + Clone + Default,
     > ChunkBoundary<V, R>
 {
+    /// Gathers the render data of a chunk and the voxels bordering it. Voxels with `RenderedVoxel::variant_weights`
+    /// pick their variant as if the chunk was at the origin, use `new_at` to pick them where the chunk really is
+    #[deprecated(note = "use new_at")]
     pub fn new(
         center: ChunkData<V, R>,
         neighbors: [ChunkData<V, R>; 26],
         voxel_registry: &R,
         geo_table: &GeometryRegistry,
         asset_registry: &AssetRegistry,
+    ) -> Self {
+        Self::new_at(
+            center,
+            neighbors,
+            ChunkPos::new(0, 0, 0),
+            voxel_registry,
+            geo_table,
+            asset_registry,
+        )
+    }
+
+    /// `new` for the chunk at chunk_pos so voxels with `RenderedVoxel::variant_weights` pick their variant from
+    /// their world position
+    pub fn new_at(
+        center: ChunkData<V, R>,
+        neighbors: [ChunkData<V, R>; 26],
+        chunk_pos: ChunkPos,
+        voxel_registry: &R,
+        geo_table: &GeometryRegistry,
+        asset_registry: &AssetRegistry,
+    ) -> Self {
+        Self::with_scale(
+            center,
+            neighbors,
+            chunk_pos,
+            1,
+            voxel_registry,
+            geo_table,
+            asset_registry,
+        )
+    }
+
+    /// `new_at` for chunks whose voxels are scale voxels wide like LOD chunks. chunk_pos is the lowest full
+    /// resolution chunk the center covers so every voxel picks the variant of the first voxel it stands for
+    pub(crate) fn with_scale(
+        center: ChunkData<V, R>,
+        neighbors: [ChunkData<V, R>; 26],
+        chunk_pos: ChunkPos,
+        scale: i32,
+        voxel_registry: &R,
+        geo_table: &GeometryRegistry,
+        asset_registry: &AssetRegistry,
     ) -> Self {
         const MAX: usize = CHUNK_SIZE;
         const BOUND: usize = MAX + 1;
         let mut geo_pal = GeoPalette::default();
         let mut matching_voxels = BlockMatches::default();
+        // In the same order as a 3x3x3 grid of chunks with x as the slowest axis
+        let chunks = neighbors[..13]
+            .iter()
+            .chain([&center])
+            .chain(neighbors[13..].iter())
+            .collect::<Vec<_>>();
+        let mut tables = chunks
+            .iter()
+            .map(|chunk| RenderTable::new(chunk))
+            .collect::<Vec<_>>();
+        // Which chunk of the grid a boundary coordinate is in and where in that chunk
+        let section = |pos: usize| match pos {
+            0 => (0, MAX - 1),
+            BOUND => (2, 0),
+            pos => (1, pos - 1),
+        };
         let voxels: Box<[RenderedBlockData; BoundaryShape::SIZE]> = (0..BoundaryShape::SIZE)
             .map(|idx| {
                 let [x, y, z] = BoundaryShape::delinearize(idx);
-                match (x, y, z) {
-                    (0, 0, 0) => get_rend(
-                        &neighbors[0],
-                        MAX - 1,
-                        MAX - 1,
-                        MAX - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (0, 0, 1..=MAX) => get_rend(
-                        &neighbors[1],
-                        MAX - 1,
-                        MAX - 1,
-                        z - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (0, 0, BOUND) => get_rend(
-                        &neighbors[2],
-                        MAX - 1,
-                        MAX - 1,
-                        0,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (0, 1..=MAX, 0) => get_rend(
-                        &neighbors[3],
-                        MAX - 1,
-                        y - 1,
-                        MAX - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (0, 1..=MAX, 1..=MAX) => get_rend(
-                        &neighbors[4],
-                        MAX - 1,
-                        y - 1,
-                        z - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (0, 1..=MAX, BOUND) => get_rend(
-                        &neighbors[5],
-                        MAX - 1,
-                        y - 1,
-                        0,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (0, BOUND, 0) => get_rend(
-                        &neighbors[6],
-                        MAX - 1,
-                        0,
-                        MAX - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (0, BOUND, 1..=MAX) => get_rend(
-                        &neighbors[7],
-                        MAX - 1,
-                        0,
-                        z - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (0, BOUND, BOUND) => get_rend(
-                        &neighbors[8],
-                        MAX - 1,
-                        0,
-                        0,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (1..=MAX, 0, 0) => get_rend(
-                        &neighbors[9],
-                        x - 1,
-                        MAX - 1,
-                        MAX - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (1..=MAX, 0, 1..=MAX) => get_rend(
-                        &neighbors[10],
-                        x - 1,
-                        MAX - 1,
-                        z - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (1..=MAX, 0, BOUND) => get_rend(
-                        &neighbors[11],
-                        x - 1,
-                        MAX - 1,
-                        0,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (1..=MAX, 1..=MAX, 0) => get_rend(
-                        &neighbors[12],
-                        x - 1,
-                        y - 1,
-                        MAX - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (1..=MAX, 1..=MAX, 1..=MAX) => get_rend(
-                        &center,
-                        x - 1,
-                        y - 1,
-                        z - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (1..=MAX, 1..=MAX, BOUND) => get_rend(
-                        &neighbors[13],
-                        x - 1,
-                        y - 1,
-                        0,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (1..=MAX, BOUND, 0) => get_rend(
-                        &neighbors[14],
-                        x - 1,
-                        0,
-                        MAX - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (1..=MAX, BOUND, 1..=MAX) => get_rend(
-                        &neighbors[15],
-                        x - 1,
-                        0,
-                        z - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (1..=MAX, BOUND, BOUND) => get_rend(
-                        &neighbors[16],
-                        x - 1,
-                        0,
-                        0,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (BOUND, 0, 0) => get_rend(
-                        &neighbors[17],
-                        0,
-                        MAX - 1,
-                        MAX - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (BOUND, 0, 1..=MAX) => get_rend(
-                        &neighbors[18],
-                        0,
-                        MAX - 1,
-                        z - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (BOUND, 0, BOUND) => get_rend(
-                        &neighbors[19],
-                        0,
-                        MAX - 1,
-                        0,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (BOUND, 1..=MAX, 0) => get_rend(
-                        &neighbors[20],
-                        0,
-                        y - 1,
-                        MAX - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (BOUND, 1..=MAX, 1..=MAX) => get_rend(
-                        &neighbors[21],
-                        0,
-                        y - 1,
-                        z - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (BOUND, 1..=MAX, BOUND) => get_rend(
-                        &neighbors[22],
-                        0,
-                        y - 1,
-                        0,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (BOUND, BOUND, 0) => get_rend(
-                        &neighbors[23],
-                        0,
-                        0,
-                        MAX - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (BOUND, BOUND, 1..=MAX) => get_rend(
-                        &neighbors[24],
-                        0,
-                        0,
-                        z - 1,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-                    (BOUND, BOUND, BOUND) => get_rend(
-                        &neighbors[25],
-                        0,
-                        0,
-                        0,
-                        geo_table,
-                        voxel_registry,
-                        asset_registry,
-                        &mut geo_pal,
-                        //texture_atlas,
-                        &mut matching_voxels,
-                    ),
-
-                    (_, _, _) => RenderedBlockData::default(),
-                }
+                let ((chunk_x, x), (chunk_y, y), (chunk_z, z)) =
+                    (section(x), section(y), section(z));
+                let chunk_idx = chunk_x * 9 + chunk_y * 3 + chunk_z;
+                let pos = RelativeVoxelPos::new(x as u32, y as u32, z as u32);
+                let chunk = chunks[chunk_idx];
+                // Where the first full resolution voxel this voxel stands for is in the world
+                let world = |chunk: usize, pos: usize, offset: i32| {
+                    (offset + (chunk as i32 - 1) * scale) * MAX as i32 + pos as i32 * scale
+                };
+                let world_pos = mint::Vector3 {
+                    x: world(chunk_x, x, chunk_pos.x),
+                    y: world(chunk_y, y, chunk_pos.y),
+                    z: world(chunk_z, z, chunk_pos.z),
+                };
+                let data = tables[chunk_idx].get(
+                    chunk.get_palette_index(pos),
+                    |weights| pick_variant(world_pos, weights),
+                    || chunk.get(pos).variant_weights(Some(voxel_registry)),
+                    |variant| {
+                        rendered_data(
+                            &chunk.get(pos),
+                            variant,
+                            geo_table,
+                            voxel_registry,
+                            asset_registry,
+                            &mut geo_pal,
+                            &mut matching_voxels,
+                        )
+                    },
+                );
+                with_light(data, chunk, pos)
             })
             .collect::<Vec<_>>()
             .try_into()
             .unwrap();
+        let voxels = effective_light(voxels, &geo_pal);
 
         Self {
             voxels,
@@ -467,35 +286,149 @@ impl<
     }
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn get_rend<
+/// Voxels with geometry that doesn't fill the whole voxel (slabs, stairs, crosses) are drawn partly inside their own
+/// cell. Opaque ones store no light of their own so give them the light of their brightest neighbor
+fn effective_light(
+    mut voxels: Box<[RenderedBlockData; BoundaryShape::SIZE]>,
+    geo_pal: &GeoPalette,
+) -> Box<[RenderedBlockData; BoundaryShape::SIZE]> {
+    let stored = voxels
+        .iter()
+        .map(|voxel| (voxel.sky_light, voxel.block_light, voxel.colored_light))
+        .collect::<Vec<_>>();
+    for idx in 0..BoundaryShape::SIZE {
+        let voxel = &mut voxels[idx];
+        let (Some(sky_light), Some(block_light), Some(colored_light)) =
+            (voxel.sky_light, voxel.block_light, voxel.colored_light)
+        else {
+            continue;
+        };
+        let full = geo_pal
+            .palette
+            .get(voxel.geo_index.unwrap_or_default())
+            .is_none_or(|geo| geo.is_full_cube());
+        if voxel.visibility == VoxelVisibility::Empty || full {
+            continue;
+        }
+        let [x, y, z] = BoundaryShape::delinearize(idx);
+        let (brightest_sky, brightest_block, brightest_colored) = [
+            (x.wrapping_sub(1), y, z),
+            (x + 1, y, z),
+            (x, y.wrapping_sub(1), z),
+            (x, y + 1, z),
+            (x, y, z.wrapping_sub(1)),
+            (x, y, z + 1),
+        ]
+        .into_iter()
+        .filter(|(x, y, z)| *x < BOUNDARY_EDGE && *y < BOUNDARY_EDGE && *z < BOUNDARY_EDGE)
+        .map(|(x, y, z)| stored[BoundaryShape::linearize([x, y, z])])
+        .fold(
+            (0, 0, [0; 3]),
+            |(sky, block, colored): (u8, u8, [u8; 3]),
+             (neighbor_sky, neighbor_block, neighbor_colored)| {
+                let neighbor_colored = neighbor_colored.unwrap_or_default();
+                (
+                    sky.max(neighbor_sky.unwrap_or_default()),
+                    block.max(neighbor_block.unwrap_or_default()),
+                    [0, 1, 2].map(|color| colored[color].max(neighbor_colored[color])),
+                )
+            },
+        );
+        let sky_light = sky_light.max(brightest_sky.saturating_sub(1));
+        let block_light = block_light.max(brightest_block.saturating_sub(1));
+        voxel.sky_light = Some(sky_light);
+        voxel.block_light = Some(block_light);
+        voxel.light = Some(sky_light.max(block_light));
+        voxel.colored_light = Some(
+            [0, 1, 2]
+                .map(|color| colored_light[color].max(brightest_colored[color].saturating_sub(1))),
+        );
+    }
+    voxels
+}
+
+/// Render data of every palette entry of a chunk. Each entry is resolved the first time a voxel using it is seen so
+/// the registry lookups happen once per voxel type and variant instead of once per voxel
+struct RenderTable {
+    entries: Vec<Option<TableEntry>>,
+}
+
+struct TableEntry {
+    /// Empty for voxels without variants
+    weights: Vec<u32>,
+    /// Render data without a variant followed by the data of every variant
+    data: Vec<Option<RenderedBlockData>>,
+}
+
+impl RenderTable {
+    fn new<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+        chunk: &ChunkData<V, R>,
+    ) -> Self {
+        Self {
+            entries: (0..chunk.palette().len()).map(|_| None).collect(),
+        }
+    }
+
+    fn get(
+        &mut self,
+        palette_index: usize,
+        pick: impl FnOnce(&[u32]) -> Option<usize>,
+        weights: impl FnOnce() -> Vec<u32>,
+        resolve: impl FnOnce(Option<usize>) -> RenderedBlockData,
+    ) -> RenderedBlockData {
+        let entry = self.entries[palette_index].get_or_insert_with(|| {
+            let weights = weights();
+            TableEntry {
+                data: vec![None; weights.len() + 1],
+                weights,
+            }
+        });
+        let variant = if entry.weights.is_empty() {
+            None
+        } else {
+            pick(&entry.weights)
+        };
+        *entry.data[variant.map_or(0, |variant| variant + 1)]
+            .get_or_insert_with(|| resolve(variant))
+    }
+}
+
+/// Everything about a voxel needed for rendering that doesn't depend on where it is besides its picked variant.
+/// Light is left unset
+pub fn rendered_data<
     V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
     R: VoxRegistry<V> + Clone + Default,
 >(
-    chunk: &ChunkData<V, R>,
-    x: usize,
-    y: usize,
-    z: usize,
+    voxel: &V,
+    variant: Option<usize>,
     geo_registry: &GeometryRegistry,
     vox_registry: &R,
     asset_registry: &AssetRegistry,
     geo_pal: &mut GeoPalette,
-    // texture_atlas: &TextureAtlas,
     matching_blocks: &mut BlockMatches,
 ) -> RenderedBlockData {
-    let (x, y, z) = (x as u32, y as u32, z as u32);
-    let voxel = chunk.get(RelativeVoxelPos::new(x, y, z));
-    let geo_index = voxel.to_geo_idx(Some(geo_pal), Some(geo_registry), Some(vox_registry));
+    let geo_index = voxel.to_geo_idx_variant(
+        Some(geo_pal),
+        Some(geo_registry),
+        Some(vox_registry),
+        variant,
+    );
     let match_index = voxel.to_match_idx(Some(matching_blocks));
     let visibility = voxel.to_visibility(Some(vox_registry), None);
-    let blocks_tuple = voxel.blocking_sides(Some(vox_registry), Some(geo_registry));
-    let textures = voxel.to_texture_uv(Some(vox_registry), Some(asset_registry));
+    let blocks_tuple =
+        voxel.blocking_sides_variant(Some(vox_registry), Some(geo_registry), variant);
+    let textures = voxel.to_texture_uv_variant(Some(vox_registry), Some(asset_registry), variant);
+    let texture_layers = voxel.to_texture_layers(Some(vox_registry), Some(asset_registry), variant);
+    let emission = voxel.emission(Some(vox_registry)).level;
+    let smooth = voxel.is_smooth(Some(vox_registry));
+    let connected = voxel.connected_textures(Some(vox_registry));
     RenderedBlockData {
         geo_index,
         // direction: voxel.direction,
         // top: voxel.top,
         match_index,
         textures,
+        texture_layers,
         visibility: visibility.unwrap_or_default(),
         // has_direction: block_data.has_direction.unwrap_or(false),
         // exclusive_direction: block_data.exclusive_direction.unwrap_or(false),
@@ -506,10 +439,97 @@ pub fn get_rend<
         blocks_self: blocks_tuple
             .unwrap_or(([true, true, true, true, true, true], None))
             .1,
-        light: None,
+        emission,
+        smooth,
+        connected,
+        ..Default::default()
     }
 }
 
+/// Fills in the stored light of the voxel at pos if the chunk has been lit
+fn with_light<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    mut data: RenderedBlockData,
+    chunk: &ChunkData<V, R>,
+    pos: RelativeVoxelPos,
+) -> RenderedBlockData {
+    if chunk.is_lit() {
+        let (sky_light, block_light) = (chunk.get_sky_light(pos), chunk.get_block_light(pos));
+        data.sky_light = Some(sky_light);
+        data.block_light = Some(block_light);
+        data.light = Some(sky_light.max(block_light));
+        data.colored_light = Some(chunk.get_colored_light(pos));
+    }
+    data
+}
+
+/// Looks up the render data of a single voxel of chunk. Like `ChunkBoundary::new` voxels with
+/// `RenderedVoxel::variant_weights` pick their variant as if the chunk was at the origin, see `get_rend_at`
+#[deprecated(note = "use get_rend_at")]
+#[allow(clippy::too_many_arguments)]
+pub fn get_rend<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    chunk: &ChunkData<V, R>,
+    x: usize,
+    y: usize,
+    z: usize,
+    geo_registry: &GeometryRegistry,
+    vox_registry: &R,
+    asset_registry: &AssetRegistry,
+    geo_pal: &mut GeoPalette,
+    // texture_atlas: &TextureAtlas,
+    matching_blocks: &mut BlockMatches,
+) -> RenderedBlockData {
+    get_rend_at(
+        chunk,
+        ChunkPos::new(0, 0, 0),
+        x,
+        y,
+        z,
+        geo_registry,
+        vox_registry,
+        asset_registry,
+        geo_pal,
+        matching_blocks,
+    )
+}
+
+/// `get_rend` for a chunk at chunk_pos so the voxel picks the same variant as in `ChunkBoundary::new_at`
+#[allow(clippy::too_many_arguments)]
+pub fn get_rend_at<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    chunk: &ChunkData<V, R>,
+    chunk_pos: ChunkPos,
+    x: usize,
+    y: usize,
+    z: usize,
+    geo_registry: &GeometryRegistry,
+    vox_registry: &R,
+    asset_registry: &AssetRegistry,
+    geo_pal: &mut GeoPalette,
+    matching_blocks: &mut BlockMatches,
+) -> RenderedBlockData {
+    let pos = RelativeVoxelPos::new(x as u32, y as u32, z as u32);
+    let voxel = chunk.get(pos);
+    let variant = pick_variant(
+        *VoxelPos::from((pos, chunk_pos)),
+        &voxel.variant_weights(Some(vox_registry)),
+    );
+    let data = rendered_data(
+        &voxel,
+        variant,
+        geo_registry,
+        vox_registry,
+        asset_registry,
+        geo_pal,
+        matching_blocks,
+    );
+    with_light(data, chunk, pos)
+}
+
 #[cfg(feature = "render")]
 #[derive(Debug, PartialEq, Clone, Copy)]
 /// This is the data that is actually used for rendering. We store it seperatly for performance
@@ -518,9 +538,21 @@ pub struct RenderedBlockData {
     pub match_index: usize,
     pub visibility: VoxelVisibility,
     pub textures: Option<[UVRect; 6]>,
+    pub texture_layers: Option<[u32; 6]>,
     pub blocks: [bool; 6],
     pub blocks_self: Option<[bool; 6]>,
+    /// The brighter of sky and block light. None if the chunk hasn't been lit
     pub light: Option<u8>,
+    pub sky_light: Option<u8>,
+    pub block_light: Option<u8>,
+    /// Red, green and blue light from the colored light mode
+    pub colored_light: Option<[u8; 3]>,
+    /// Light level the voxel itself gives off
+    pub emission: u8,
+    /// Part of the smooth surface instead of being meshed as a block
+    pub smooth: bool,
+    /// Picks the tile of each face from the neighbors with the same `match_index`
+    pub connected: Option<ConnectedTextures>,
 }
 
 #[cfg(feature = "render")]
@@ -532,9 +564,144 @@ impl Default for RenderedBlockData {
             blocks_self: None,
             // tex_variance: [false, false, false, false, false, false],
             textures: None,
+            texture_layers: None,
             geo_index: None,
             match_index: 0,
             light: None,
+            sky_light: None,
+            block_light: None,
+            colored_light: None,
+            emission: 0,
+            smooth: false,
+            connected: None,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn table_matches_lookups() {
+        let (vox_registry, geo_registry, asset_registry) = registries();
+        let names = ["stone", "glass", "slab", "air"];
+        let mut center = ChunkData::<BlockData, BlockRegistry>::default();
+        for x in 0..CHUNK_SIZE as u32 {
+            for y in 0..CHUNK_SIZE as u32 {
+                for z in 0..CHUNK_SIZE as u32 {
+                    let name = names[((x * 7 + y * 3 + z) % 4) as usize];
+                    center.set(RelativeVoxelPos::new(x, y, z), block(name));
+                }
+            }
+        }
+        let chunk_pos = ChunkPos::new(2, -1, 3);
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            center.clone(),
+            std::array::from_fn(|_| ChunkData::default()),
+            chunk_pos,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+
+        let mut geo_pal = GeoPalette::default();
+        let mut matches = BlockMatches::default();
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let looked_up = get_rend_at(
+                        &center,
+                        chunk_pos,
+                        x,
+                        y,
+                        z,
+                        &geo_registry,
+                        &vox_registry,
+                        &asset_registry,
+                        &mut geo_pal,
+                        &mut matches,
+                    );
+                    let from_table = boundary.voxels()
+                        [ChunkBoundary::<BlockData, BlockRegistry>::linearize(x + 1, y + 1, z + 1)];
+                    assert_eq!(from_table.visibility, looked_up.visibility);
+                    assert_eq!(from_table.textures, looked_up.textures);
+                    assert_eq!(from_table.blocks, looked_up.blocks);
+                    assert_eq!(from_table.blocks_self, looked_up.blocks_self);
+                    assert_eq!(
+                        from_table
+                            .geo_index
+                            .map(|index| &boundary.geometry_pal.palette[index]),
+                        looked_up.geo_index.map(|index| &geo_pal.palette[index])
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn picks_variants() {
+        let mut counts = [0; 2];
+        for x in 0..64 {
+            for z in 0..64 {
+                counts[pick_variant(mint::Vector3 { x, y: 5, z }, &[3, 1]).unwrap()] += 1;
+            }
+        }
+        assert!(counts[0] > counts[1] * 2 && counts[1] > 0);
+        assert_eq!(pick_variant(mint::Vector3 { x: 1, y: 2, z: 3 }, &[]), None);
+        assert_eq!(
+            pick_variant(mint::Vector3 { x: 1, y: 2, z: 3 }, &[0, 0]),
+            None
+        );
+
+        let (vox_registry, geo_registry, asset_registry) = rock_registries();
+        let mut center = ChunkData::<BlockData, BlockRegistry>::default();
+        for x in 0..CHUNK_SIZE as u32 {
+            for z in 0..CHUNK_SIZE as u32 {
+                center.set(RelativeVoxelPos::new(x, 0, z), block("rock"));
+            }
+        }
+        for chunk_pos in [ChunkPos::new(0, 0, 0), ChunkPos::new(3, -2, 7)] {
+            let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+                center.clone(),
+                std::array::from_fn(|_| ChunkData::default()),
+                chunk_pos,
+                &vox_registry,
+                &geo_registry,
+                &asset_registry,
+            );
+            let mut geo_pal = GeoPalette::default();
+            let mut matches = BlockMatches::default();
+            for x in 0..CHUNK_SIZE {
+                for z in 0..CHUNK_SIZE {
+                    let data = boundary.voxels()
+                        [ChunkBoundary::<BlockData, BlockRegistry>::linearize(x + 1, 1, z + 1)];
+                    let world_pos =
+                        VoxelPos::from((RelativeVoxelPos::new(x as u32, 0, z as u32), chunk_pos));
+                    let slab = pick_variant(*world_pos, &[3, 1]) == Some(1);
+                    let geo = &boundary.geometry_pal.palette[data.geo_index.unwrap()];
+                    assert_eq!(geo.cubes[0].end.1 == 8, slab);
+                    assert_eq!(data.textures.unwrap()[0].x, if slab { 16. } else { 0. });
+                    assert_eq!(data.blocks[3], !slab);
+
+                    // Single lookups pick the same variant
+                    let looked_up = get_rend_at(
+                        &center,
+                        chunk_pos,
+                        x,
+                        0,
+                        z,
+                        &geo_registry,
+                        &vox_registry,
+                        &asset_registry,
+                        &mut geo_pal,
+                        &mut matches,
+                    );
+                    assert_eq!(looked_up.textures, data.textures);
+                    assert_eq!(looked_up.blocks, data.blocks);
+                }
+            }
         }
     }
 }
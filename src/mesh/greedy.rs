@@ -0,0 +1,245 @@
+use serde::Serialize;
+
+use crate::prelude::*;
+
+/// Everything that has to match for two neighboring faces to be drawn as one quad.
+/// AO and light are the same at every corner of a face since a merged quad only has the corners of its first face
+#[derive(PartialEq)]
+struct MergeKey {
+    match_index: usize,
+    geo_index: Option<usize>,
+    textures: Option<[UVRect; 6]>,
+    texture_layers: Option<[u32; 6]>,
+    emission: u8,
+    ao: u32,
+    light: Vec<f32>,
+}
+
+/// Merges coplanar faces of full cubes that look the same (texture, AO and light) into bigger quads.
+/// Faces of custom geometry, faces with texture variance or connected textures and faces whose AO or light
+/// changes across the face are left as they are.
+/// Run this on the output of `generate_mesh`
+pub fn greedy_merge<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    chunk: &ChunkBoundary<V, R>,
+    buffer: &mut QuadGroups,
+    options: &MeshOptions,
+) {
+    const SIZE: usize = CHUNK_SIZE;
+    for (side_index, quads) in buffer.groups.iter_mut().enumerate() {
+        let side = Side::from(side_index);
+        let (normal_axis, one_axis, two_axis) = match side.axis {
+            Axis::X => (0, 1, 2),
+            Axis::Y => (1, 0, 2),
+            Axis::Z => (2, 0, 1),
+        };
+
+        let mut slices: Vec<Vec<Option<(usize, MergeKey)>>> = (0..SIZE)
+            .map(|_| (0..SIZE * SIZE).map(|_| None).collect())
+            .collect();
+        let mut kept = Vec::with_capacity(quads.len());
+        for (quad_index, quad) in quads.iter().enumerate() {
+            let full = quad.start == (0, 0, 0)
+                && quad.end == (16, 16, 16)
+                && chunk
+                    .geometry_pal
+                    .palette
+                    .get(quad.data.geo_index.unwrap_or_default())
+                    .is_some_and(|geo| {
+                        // Every face with texture variance is rotated differently so they can't share a quad
+                        geo.is_full_cube() && !geo.cubes[0].texture_variance[side_index]
+                    })
+                // Connected faces pick their own tile
+                && quad.data.connected.is_none();
+            let key = full
+                .then(|| merge_key(&Face { side, quad }, chunk, options))
+                .flatten();
+            let Some(key) = key else {
+                kept.push(quad.clone());
+                continue;
+            };
+            let voxel = quad.voxel;
+            slices[voxel[normal_axis] - 1][(voxel[two_axis] - 1) * SIZE + voxel[one_axis] - 1] =
+                Some((quad_index, key));
+        }
+
+        for slice in slices.iter_mut() {
+            for two in 0..SIZE {
+                let mut one = 0;
+                while one < SIZE {
+                    let Some((quad_index, key)) = slice[two * SIZE + one].take() else {
+                        one += 1;
+                        continue;
+                    };
+                    let mut width = 1;
+                    while one + width < SIZE
+                        && slice[two * SIZE + one + width]
+                            .as_ref()
+                            .is_some_and(|(_, other)| *other == key)
+                    {
+                        width += 1;
+                    }
+                    let mut height = 1;
+                    'grow: while two + height < SIZE {
+                        for offset in 0..width {
+                            if !slice[(two + height) * SIZE + one + offset]
+                                .as_ref()
+                                .is_some_and(|(_, other)| *other == key)
+                            {
+                                break 'grow;
+                            }
+                        }
+                        height += 1;
+                    }
+                    for row in 0..height {
+                        for offset in 0..width {
+                            slice[(two + row) * SIZE + one + offset] = None;
+                        }
+                    }
+
+                    let mut merged = quads[quad_index].clone();
+                    merged.extent = (width as u8, height as u8);
+                    kept.push(merged);
+                    one += width;
+                }
+            }
+        }
+        *quads = kept;
+    }
+}
+
+/// The value shared by all 4 corners or None if they differ
+fn uniform<T: PartialEq + Copy>(corners: [T; 4]) -> Option<T> {
+    corners[1..]
+        .iter()
+        .all(|corner| *corner == corners[0])
+        .then_some(corners[0])
+}
+
+/// None if the AO or light isn't the same at every corner of the face
+fn merge_key<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    face: &Face,
+    chunk: &ChunkBoundary<V, R>,
+    options: &MeshOptions,
+) -> Option<MergeKey> {
+    let mut light = Vec::new();
+    match (options.light_mode, options.colored_light) {
+        (LightMode::Baked, false) => light.push(uniform(face_lights(face, chunk))?),
+        (LightMode::Baked, true) => light.extend(uniform(face_colored_lights(face, chunk))?),
+        (LightMode::Separate, colored) => {
+            light.push(uniform(face_light_levels(face, chunk, LightChannel::Sky))?);
+            light.push(uniform(face_light_levels(
+                face,
+                chunk,
+                LightChannel::Block,
+            ))?);
+            if colored {
+                for channel in LightChannel::COLORS {
+                    light.push(uniform(face_light_levels(face, chunk, channel))?);
+                }
+            }
+        }
+    }
+    let data = &face.quad.data;
+    Some(MergeKey {
+        match_index: data.match_index,
+        geo_index: data.geo_index,
+        textures: data.textures,
+        texture_layers: data.texture_layers,
+        emission: data.emission,
+        ao: uniform(face_aos(face, chunk))?,
+        light,
+    })
+}
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    const CENTER: ChunkPos = ChunkPos(mint::Vector3 { x: 0, y: 0, z: 0 });
+
+    // A stone floor at y 0 across every chunk around the center
+    fn floor() -> ChunkMap<BlockData, BlockRegistry> {
+        let mut world = ChunkMap::default();
+        for pos in CENTER.neighbors().iter().chain([CENTER].iter()) {
+            world.insert(*pos, ChunkData::default());
+        }
+        for x in -16..32 {
+            for z in -16..32 {
+                world.set_voxel(VoxelPos::new(x, 0, z), block("stone"));
+            }
+        }
+        world
+    }
+
+    fn mesh(world: &ChunkMap<BlockData, BlockRegistry>, greedy: bool) -> VoxMesh {
+        let (vox_registry, geo_registry, asset_registry) = registries();
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            world[&CENTER].clone(),
+            world.neighbors(CENTER).unwrap(),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        let options = MeshOptions {
+            greedy,
+            ..Default::default()
+        };
+        full_mesh_with_options(&asset_registry, &boundary, *CENTER, &options).chunk_mesh
+    }
+
+    // Number of up facing quads with their top at y
+    fn top_quads(mesh: &VoxMesh, y: f32) -> usize {
+        mesh.vertices
+            .iter()
+            .zip(&mesh.normals)
+            .filter(|(vertex, normal)| normal[1] > 0.5 && vertex[1] == y)
+            .count()
+            / 4
+    }
+
+    #[test]
+    fn merges_floor() {
+        let world = floor();
+        let plain = mesh(&world, false);
+        // 256 top and 256 bottom faces
+        assert_eq!(plain.vertices.len(), 512 * 4);
+        let greedy = mesh(&world, true);
+        assert_eq!(greedy.vertices.len(), 2 * 4);
+        assert_eq!(greedy.indices.len(), 2 * 6);
+        assert_eq!(greedy.uv_rects.as_ref().unwrap().len(), 8);
+        let top = greedy
+            .vertices
+            .iter()
+            .filter(|vertex| vertex[1] == 1.0)
+            .collect::<Vec<_>>();
+        assert_eq!(top.len(), 4);
+        assert!(top
+            .iter()
+            .all(|vertex| [0.0, 16.0].contains(&vertex[0]) && [0.0, 16.0].contains(&vertex[2])));
+    }
+
+    #[test]
+    fn keeps_shaded_faces() {
+        let mut world = floor();
+        for x in -16..32 {
+            world.set_voxel(VoxelPos::new(x, 1, 8), block("stone"));
+        }
+        let greedy = mesh(&world, true);
+        // The rows on both sides of the wall are shaded on one edge so each of their faces stays on its own.
+        // The open floor on either side and the top of the wall are merged
+        assert_eq!(top_quads(&greedy, 1.0), 1 + 16 + 16 + 1);
+        assert_eq!(top_quads(&greedy, 2.0), 1);
+        assert_eq!(
+            top_quads(&mesh(&world, false), 1.0),
+            top_quads(&greedy, 1.0) + 7 * 16 + 6 * 16 - 2
+        );
+    }
+}
@@ -0,0 +1,451 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::prelude::*;
+
+/// Highest supported level. At this level every voxel of a LOD chunk covers a whole chunk
+pub const MAX_LOD: u8 = 4;
+
+/// How many chunks wide a LOD chunk of the given level is. This is also how many voxels wide each of its voxels is
+pub fn lod_scale(level: u8) -> i32 {
+    1 << level.min(MAX_LOD)
+}
+
+/// The lowest full resolution chunk covered by a LOD chunk. LOD chunk positions are in units of `lod_scale` chunks
+pub fn lod_origin(lod_pos: ChunkPos, level: u8) -> ChunkPos {
+    let scale = lod_scale(level);
+    ChunkPos::new(lod_pos.x * scale, lod_pos.y * scale, lod_pos.z * scale)
+}
+
+/// Downsamples the cube of `lod_scale` chunks per axis starting at `lod_origin` into a single chunk. Every voxel of
+/// the result stands for a cube of `lod_scale` voxels per axis. It is filled if at least half of that cube isn't
+/// empty and takes the most common non empty voxel in it, preferring opaque voxels on ties so surfaces stay visible.
+/// Light is the brightest light in the cube and the result is only lit when all of its loaded chunks are.
+/// Chunks that aren't loaded count as empty. Returns None when none of them are loaded
+pub fn downsample_lod<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    world: &ChunkMap<V, R>,
+    registry: &R,
+    lod_pos: ChunkPos,
+    level: u8,
+) -> Option<ChunkData<V, R>> {
+    let full = 0..CHUNK_SIZE;
+    downsample_region(
+        world,
+        registry,
+        lod_pos,
+        level,
+        [full.clone(), full.clone(), full],
+    )
+}
+
+/// `downsample_lod` for only the voxels of the LOD chunk inside region. Only the source chunks under region are read
+fn downsample_region<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    world: &ChunkMap<V, R>,
+    registry: &R,
+    lod_pos: ChunkPos,
+    level: u8,
+    region: [Range<usize>; 3],
+) -> Option<ChunkData<V, R>> {
+    let level = level.min(MAX_LOD);
+    let scale = lod_scale(level);
+    let origin = lod_origin(lod_pos, level);
+    // Voxels of the result that each source chunk covers per axis
+    let span = CHUNK_SIZE / scale as usize;
+
+    let mut lod = ChunkData::<V, R>::default();
+    let mut loaded = false;
+    let mut lit = true;
+    for chunk_x in 0..scale {
+        for chunk_y in 0..scale {
+            for chunk_z in 0..scale {
+                // The voxels of the result covered by this source chunk that are inside region
+                let offset = [chunk_x, chunk_y, chunk_z].map(|offset| offset as usize * span);
+                let covered = [0, 1, 2].map(|axis| {
+                    region[axis].start.max(offset[axis])..region[axis].end.min(offset[axis] + span)
+                });
+                if covered.iter().any(|range| range.is_empty()) {
+                    continue;
+                }
+                let Some(chunk) = world.get(&ChunkPos::new(
+                    origin.x + chunk_x,
+                    origin.y + chunk_y,
+                    origin.z + chunk_z,
+                )) else {
+                    continue;
+                };
+                loaded = true;
+                lit &= chunk.is_lit();
+                let palette = chunk
+                    .palette()
+                    .into_iter()
+                    .map(|voxel| {
+                        (
+                            voxel,
+                            voxel.is_empty(Some(registry)),
+                            voxel.is_opaque(Some(registry)),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                for x in covered[0].clone() {
+                    for y in covered[1].clone() {
+                        for z in covered[2].clone() {
+                            let pos = RelativeVoxelPos::new(x as u32, y as u32, z as u32);
+                            let source = [x - offset[0], y - offset[1], z - offset[2]]
+                                .map(|axis| axis * scale as usize);
+                            downsample_voxel(
+                                chunk,
+                                &palette,
+                                source,
+                                scale as usize,
+                                &mut lod,
+                                pos,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+    lod.set_lit(loaded && lit);
+    loaded.then_some(lod)
+}
+
+/// Fills one voxel of a LOD chunk from the cube of voxels of a full resolution chunk starting at source
+fn downsample_voxel<V: Voxel<R> + Clone + Eq + Default, R: VoxRegistry<V> + Clone + Default>(
+    chunk: &ChunkData<V, R>,
+    // (voxel, empty, opaque) for every palette entry of the chunk
+    palette: &[(&V, bool, bool)],
+    source: [usize; 3],
+    scale: usize,
+    lod: &mut ChunkData<V, R>,
+    pos: RelativeVoxelPos,
+) {
+    // (palette index, count)
+    let mut counts: Vec<(usize, usize)> = Vec::new();
+    let mut filled = 0;
+    let (mut sky_light, mut block_light, mut colored_light) = (0, 0, [0; 3]);
+    for x in source[0]..source[0] + scale {
+        for y in source[1]..source[1] + scale {
+            for z in source[2]..source[2] + scale {
+                let source_pos = RelativeVoxelPos::new(x as u32, y as u32, z as u32);
+                sky_light = chunk.get_sky_light(source_pos).max(sky_light);
+                block_light = chunk.get_block_light(source_pos).max(block_light);
+                let colored = chunk.get_colored_light(source_pos);
+                colored_light = [0, 1, 2].map(|color| colored[color].max(colored_light[color]));
+
+                let index = chunk.get_palette_index(source_pos);
+                if palette[index].1 {
+                    continue;
+                }
+                filled += 1;
+                match counts.iter_mut().find(|(counted, _)| *counted == index) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((index, 1)),
+                }
+            }
+        }
+    }
+
+    lod.set_sky_light(pos, sky_light);
+    lod.set_block_light(pos, block_light);
+    for (color, level) in colored_light.into_iter().enumerate() {
+        lod.set_colored_light(pos, color, level);
+    }
+    if filled * 2 < scale * scale * scale {
+        return;
+    }
+    if let Some((index, _)) = counts
+        .into_iter()
+        .max_by_key(|(index, count)| (*count, palette[*index].2))
+    {
+        lod.set(pos, palette[index].0.clone());
+    }
+}
+
+/// Builds the boundary of a LOD chunk. The layer of every one of the 26 neighbors that touches the chunk is downsampled
+/// at the same level so faces between LOD chunks of the same level are culled and AO, light and connected textures
+/// carry on across their faces, edges and corners.
+///
+/// Sides set in `open_sides` (in side order) border a chunk of another level. The neighbors on those sides are left
+/// empty so every face along that border is drawn. These walls only fill the cracks from this chunks side: the chunk
+/// on the other side of the border is meshed as usual, so open the sides of the coarser of the two chunks whose
+/// surface sits lower and whose walls reach down to the finer surface
+pub fn lod_boundary<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &ChunkMap<V, R>,
+    lod_pos: ChunkPos,
+    level: u8,
+    open_sides: [bool; 6],
+    vox_registry: &R,
+    geo_registry: &GeometryRegistry,
+    asset_registry: &AssetRegistry,
+) -> Option<ChunkBoundary<V, R>> {
+    let center = downsample_lod(world, vox_registry, lod_pos, level)?;
+    let mut neighbors: [ChunkData<V, R>; 26] = Default::default();
+    for (neighbor, neighbor_pos) in neighbors.iter_mut().zip(lod_pos.neighbors()) {
+        let offset = [
+            neighbor_pos.x - lod_pos.x,
+            neighbor_pos.y - lod_pos.y,
+            neighbor_pos.z - lod_pos.z,
+        ];
+        let open = offset
+            .iter()
+            .enumerate()
+            .any(|(axis, offset)| *offset != 0 && open_sides[axis * 2 + (*offset > 0) as usize]);
+        if open {
+            continue;
+        }
+        // The boundary only reads the layer of each neighbor facing the chunk
+        let region = offset.map(|offset| match offset {
+            -1 => CHUNK_SIZE - 1..CHUNK_SIZE,
+            1 => 0..1,
+            _ => 0..CHUNK_SIZE,
+        });
+        if let Some(downsampled) =
+            downsample_region(world, vox_registry, neighbor_pos, level, region)
+        {
+            *neighbor = downsampled;
+        }
+    }
+    Some(ChunkBoundary::with_scale(
+        center,
+        neighbors,
+        lod_origin(lod_pos, level),
+        lod_scale(level),
+        vox_registry,
+        geo_registry,
+        asset_registry,
+    ))
+}
+
+/// Downsamples and meshes a LOD chunk. Vertices are relative to the LOD chunks origin which is at
+/// `lod_pos * CHUNK_SIZE * lod_scale(level)` in world space and the mesh's pos is `lod_origin`. See `lod_boundary`
+/// for open sides
+#[allow(clippy::too_many_arguments)]
+pub fn lod_mesh<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &ChunkMap<V, R>,
+    lod_pos: ChunkPos,
+    level: u8,
+    open_sides: [bool; 6],
+    vox_registry: &R,
+    geo_registry: &GeometryRegistry,
+    asset_registry: &AssetRegistry,
+    options: &MeshOptions,
+) -> Option<MeshedChunk> {
+    let boundary = lod_boundary(
+        world,
+        lod_pos,
+        level,
+        open_sides,
+        vox_registry,
+        geo_registry,
+        asset_registry,
+    )?;
+    Some(full_mesh_with_options(
+        asset_registry,
+        &boundary,
+        *lod_origin(lod_pos, level),
+        &MeshOptions {
+            lod: level.min(MAX_LOD),
+            ..*options
+        },
+    ))
+}
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    // Three layers of stone across 6 chunks per axis
+    fn world() -> ChunkMap<BlockData, BlockRegistry> {
+        let mut world = ChunkMap::default();
+        for x in -2..=3 {
+            for y in -2..=3 {
+                for z in -2..=3 {
+                    world.insert(ChunkPos::new(x, y, z), ChunkData::default());
+                }
+            }
+        }
+        for x in -32..64 {
+            for z in -32..64 {
+                for y in 0..3 {
+                    world.set_voxel(VoxelPos::new(x, y, z), block("stone"));
+                }
+            }
+        }
+        world
+    }
+
+    fn boundary(
+        world: &ChunkMap<BlockData, BlockRegistry>,
+        open_sides: [bool; 6],
+    ) -> ChunkBoundary<BlockData, BlockRegistry> {
+        let (vox_registry, geo_registry, asset_registry) = registries();
+        lod_boundary(
+            world,
+            ChunkPos::new(0, 0, 0),
+            1,
+            open_sides,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        )
+        .unwrap()
+    }
+
+    fn visibility(
+        boundary: &ChunkBoundary<BlockData, BlockRegistry>,
+        x: usize,
+        y: usize,
+        z: usize,
+    ) -> VoxelVisibility {
+        boundary.voxels()[ChunkBoundary::<BlockData, BlockRegistry>::linearize(x, y, z)].visibility
+    }
+
+    #[test]
+    fn downsamples() {
+        let (vox_registry, ..) = registries();
+        let world = world();
+        let lod = downsample_lod(&world, &vox_registry, ChunkPos::new(0, 0, 0), 1).unwrap();
+        assert_eq!(
+            lod.get_identifier(RelativeVoxelPos::new(3, 1, 7)),
+            "vinox:stone"
+        );
+        assert_eq!(
+            lod.get_identifier(RelativeVoxelPos::new(3, 2, 7)),
+            "vinox:air"
+        );
+        assert!(downsample_lod(&world, &vox_registry, ChunkPos::new(9, 0, 0), 1).is_none());
+    }
+
+    #[test]
+    fn fills_edge_and_corner_neighbors() {
+        let world = world();
+        let boundary = boundary(&world, [false; 6]);
+        // Face, edge and corner neighbors of the bottom layer
+        for (x, z) in [(0, 5), (0, 0), (17, 17), (0, 17), (17, 0)] {
+            assert_eq!(visibility(&boundary, x, 1, z), VoxelVisibility::Opaque);
+        }
+        assert_eq!(visibility(&boundary, 0, 3, 0), VoxelVisibility::Empty);
+
+        // Neighbors touching an open side stay empty, including the edges and corners along it
+        let boundary = self::boundary(&world, [true, false, false, false, false, false]);
+        for (x, z) in [(0, 5), (0, 0), (0, 17)] {
+            assert_eq!(visibility(&boundary, x, 1, z), VoxelVisibility::Empty);
+        }
+        assert_eq!(visibility(&boundary, 17, 1, 0), VoxelVisibility::Opaque);
+    }
+
+    #[test]
+    fn meshes_levels() {
+        let (vox_registry, geo_registry, asset_registry) = registries();
+        let world = world();
+        let mesh = |level, open_sides, options| {
+            lod_mesh(
+                &world,
+                ChunkPos::new(0, 0, 0),
+                level,
+                open_sides,
+                &vox_registry,
+                &geo_registry,
+                &asset_registry,
+                &options,
+            )
+            .unwrap()
+            .chunk_mesh
+            .vertices
+        };
+        let vertices = mesh(1, [false; 6], MeshOptions::default());
+        let max = |vertices: &Vec<[f32; 3]>, axis: usize| {
+            vertices
+                .iter()
+                .map(|vertex| vertex[axis])
+                .fold(f32::MIN, f32::max)
+        };
+        // Two chunks wide with voxels two wide, and the cubes rounded up to four layers
+        assert_eq!(max(&vertices, 0), 32.0);
+        assert_eq!(max(&vertices, 1), 4.0);
+
+        // Open sides add walls along the borders
+        assert!(mesh(1, [true; 6], MeshOptions::default()).len() > vertices.len());
+
+        let greedy = mesh(
+            2,
+            [false; 6],
+            MeshOptions {
+                greedy: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(max(&greedy, 0), 64.0);
+        assert!(greedy.len() < vertices.len());
+    }
+
+    #[test]
+    fn variants_follow_world_position() {
+        let (vox_registry, geo_registry, asset_registry) = rock_registries();
+        let lod_pos = ChunkPos::new(1, 0, -1);
+        let mut world = ChunkMap::<BlockData, BlockRegistry>::default();
+        let origin = lod_origin(lod_pos, 1);
+        for x in 0..2 {
+            for z in 0..2 {
+                let pos = ChunkPos::new(origin.x + x, 0, origin.z + z);
+                world.insert(pos, ChunkData::default());
+                for voxel_x in 0..CHUNK_SIZE as u32 {
+                    for voxel_z in 0..CHUNK_SIZE as u32 {
+                        for y in 0..2 {
+                            world.set_voxel(
+                                VoxelPos::from((RelativeVoxelPos::new(voxel_x, y, voxel_z), pos)),
+                                block("rock"),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        let boundary = lod_boundary(
+            &world,
+            lod_pos,
+            1,
+            [false; 6],
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        )
+        .unwrap();
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                // Every LOD voxel looks like the first full resolution voxel it covers
+                let world_pos = mint::Vector3 {
+                    x: origin.x * CHUNK_SIZE as i32 + x as i32 * 2,
+                    y: 0,
+                    z: origin.z * CHUNK_SIZE as i32 + z as i32 * 2,
+                };
+                let slab = pick_variant(world_pos, &[3, 1]) == Some(1);
+                let data = boundary.voxels()
+                    [ChunkBoundary::<BlockData, BlockRegistry>::linearize(x + 1, 1, z + 1)];
+                assert_eq!(data.textures.unwrap()[0].x, if slab { 16. } else { 0. });
+            }
+        }
+
+        let mesh = lod_mesh(
+            &world,
+            lod_pos,
+            1,
+            [false; 6],
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+            &MeshOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(mesh.pos, origin);
+    }
+}
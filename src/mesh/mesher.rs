@@ -1,10 +1,19 @@
 use crate::prelude::*;
 use serde::Serialize;
-use std::ops::Deref;
+use std::ops::{Deref, Range};
 
 pub const EMPTY: VoxelVisibility = VoxelVisibility::Empty;
 pub const OPAQUE: VoxelVisibility = VoxelVisibility::Opaque;
 pub const TRANSPARENT: VoxelVisibility = VoxelVisibility::Transparent;
+pub const CUTOUT: VoxelVisibility = VoxelVisibility::Cutout;
+
+/// The meshes of a chunk. Each one only gets the faces of voxels with its visibility
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum MeshPass {
+    Solid,
+    Cutout,
+    Transparent,
+}
 
 #[derive(Clone, Debug)]
 pub struct Quad {
@@ -13,6 +22,14 @@ pub struct Quad {
     pub end: (i8, i8, i8),
     pub cube: usize,
     pub data: RenderedBlockData,
+    pub(crate) extent: (u8, u8),
+}
+
+impl Quad {
+    /// How many voxels the quad covers along its two face axes. Only greedy meshing merges faces into bigger quads
+    pub fn extent(&self) -> (u8, u8) {
+        self.extent
+    }
 }
 
 #[derive(Default)]
@@ -52,11 +69,35 @@ impl Side {
     pub fn normals(&self) -> [[f32; 3]; 4] {
         [self.normal(), self.normal(), self.normal(), self.normal()]
     }
+
+    /// Index of this sides texture in `RenderedBlockData::textures` and `RenderedBlockData::texture_layers`
+    pub fn texture_index(&self) -> usize {
+        match (self.axis, self.positive) {
+            (Axis::X, false) => 2,
+            (Axis::X, true) => 3,
+            (Axis::Y, false) => 1,
+            (Axis::Y, true) => 0,
+            (Axis::Z, false) => 5,
+            (Axis::Z, true) => 4,
+        }
+    }
+
+    /// Index of the side in the West, East, Down, Up, South, North order
+    pub fn index(&self) -> usize {
+        match (self.axis, self.positive) {
+            (Axis::X, false) => 0,
+            (Axis::X, true) => 1,
+            (Axis::Y, false) => 2,
+            (Axis::Y, true) => 3,
+            (Axis::Z, false) => 4,
+            (Axis::Z, true) => 5,
+        }
+    }
 }
 
 pub struct Face<'a> {
-    side: Side,
-    quad: &'a Quad,
+    pub(crate) side: Side,
+    pub(crate) quad: &'a Quad,
 }
 
 impl From<usize> for Side {
@@ -73,7 +114,7 @@ impl From<usize> for Side {
     }
 }
 impl QuadGroups {
-    pub fn iter(&self) -> impl Iterator<Item = Face> {
+    pub fn iter(&self) -> impl Iterator<Item = Face<'_>> {
         self.groups
             .iter()
             .enumerate()
@@ -749,6 +790,71 @@ pub fn face_aos<
     }
 }
 
+/// The 8 voxels around the voxel in front of a face, in the order `side_light` expects, for each side
+const LIGHT_NEIGHBORS: [[(i32, i32, i32); 8]; 6] = [
+    [
+        (0, 0, 1),
+        (0, -1, 1),
+        (0, -1, 0),
+        (0, -1, -1),
+        (0, 0, -1),
+        (0, 1, -1),
+        (0, 1, 0),
+        (0, 1, 1),
+    ],
+    [
+        (0, 0, -1),
+        (0, -1, -1),
+        (0, -1, 0),
+        (0, -1, 1),
+        (0, 0, 1),
+        (0, 1, 1),
+        (0, 1, 0),
+        (0, 1, -1),
+    ],
+    [
+        (-1, 0, 0),
+        (-1, 0, 1),
+        (0, 0, 1),
+        (1, 0, 1),
+        (1, 0, 0),
+        (1, 0, -1),
+        (0, 0, -1),
+        (-1, 0, -1),
+    ],
+    [
+        (0, 0, 1),
+        (-1, 0, 1),
+        (-1, 0, 0),
+        (-1, 0, -1),
+        (0, 0, -1),
+        (1, 0, -1),
+        (1, 0, 0),
+        (1, 0, 1),
+    ],
+    [
+        (-1, 0, 0),
+        (-1, -1, 0),
+        (0, -1, 0),
+        (1, -1, 0),
+        (1, 0, 0),
+        (1, 1, 0),
+        (0, 1, 0),
+        (-1, 1, 0),
+    ],
+    [
+        (1, 0, 0),
+        (1, -1, 0),
+        (0, -1, 0),
+        (-1, -1, 0),
+        (-1, 0, 0),
+        (-1, 1, 0),
+        (0, 1, 0),
+        (1, 1, 0),
+    ],
+];
+
+/// Smooth light for the 4 corners of a face using the combined sky and block light on the baked light curve
 pub fn face_lights<
     V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
     R: VoxRegistry<V> + Clone + Default,
@@ -756,166 +862,101 @@ pub fn face_lights<
     face: &Face,
     chunk: &ChunkBoundary<V, R>,
 ) -> [f32; 4] {
+    let (center, ring) = face_light_samples(face, chunk, |voxel| voxel.light);
+    side_light(center, ring, light_to_intern)
+}
+
+/// Smooth colored light for the 4 corners of a face on the baked light curve. Sky light is white so each color is
+/// the brighter of it and that color of the colored light
+pub fn face_colored_lights<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    face: &Face,
+    chunk: &ChunkBoundary<V, R>,
+) -> [[f32; 3]; 4] {
+    let colors = [0, 1, 2].map(|color| {
+        let (center, ring) = face_light_samples(face, chunk, |voxel| {
+            voxel
+                .sky_light
+                .zip(voxel.colored_light)
+                .map(|(sky, colored)| sky.max(colored[color]))
+        });
+        side_light(center, ring, light_to_intern)
+    });
+    [0, 1, 2, 3].map(|vertex| colors.map(|color| color[vertex]))
+}
+
+/// Smooth raw light levels (0.0..1.0) of a single channel for the 4 corners of a face. Unlit chunks read as full light
+pub fn face_light_levels<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    face: &Face,
+    chunk: &ChunkBoundary<V, R>,
+    channel: LightChannel,
+) -> [f32; 4] {
+    let (center, ring) = face_light_samples(face, chunk, |voxel| match channel {
+        LightChannel::Block => voxel.block_light,
+        LightChannel::Sky => voxel.sky_light,
+        LightChannel::Red | LightChannel::Green | LightChannel::Blue => voxel
+            .colored_light
+            .map(|colored| colored[channel.color_index().unwrap_or_default()]),
+    });
+    side_light(center, ring, |level| {
+        level.min(MAX_LIGHT) as f32 / MAX_LIGHT as f32
+    })
+}
+
+/// Light of the voxel a face sits in front of and of the 8 voxels around it. Faces on the edge of the voxel sample
+/// the layer of voxels in front of them while faces inside the voxel (slab tops, crosses) sample the voxels own layer
+fn face_light_samples<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    face: &Face,
+    chunk: &ChunkBoundary<V, R>,
+    light: impl Fn(&RenderedBlockData) -> Option<u8>,
+) -> (Option<u8>, [Option<u8>; 8]) {
     let [x, y, z] = face.voxel();
-    // let (x, y, z) = (x as u32, y as u32, z as u32);
-    match (face.side.axis, face.side.positive) {
-        (Axis::X, false) => side_light([
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y - 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y - 1, z)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y - 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y + 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y + 1, z)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y + 1, z + 1)]
-                .light
-                .unwrap_or(16),
-        ]),
-        (Axis::X, true) => side_light([
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y - 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y - 1, z)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y - 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y + 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y + 1, z)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y + 1, z - 1)]
-                .light
-                .unwrap_or(16),
-        ]),
-        (Axis::Y, false) => side_light([
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y - 1, z)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y - 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y - 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y - 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y - 1, z)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y - 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y - 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y - 1, z - 1)]
-                .light
-                .unwrap_or(16),
-        ]),
-        (Axis::Y, true) => side_light([
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y + 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y + 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y + 1, z)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y + 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y + 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y + 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y + 1, z)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y + 1, z + 1)]
-                .light
-                .unwrap_or(16),
-        ]),
-        (Axis::Z, false) => side_light([
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y - 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y - 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y - 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y + 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y + 1, z - 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y + 1, z - 1)]
-                .light
-                .unwrap_or(16),
-        ]),
-        (Axis::Z, true) => side_light([
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y - 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y - 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y - 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y + 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y + 1, z + 1)]
-                .light
-                .unwrap_or(16),
-            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y + 1, z + 1)]
-                .light
-                .unwrap_or(16),
-        ]),
-    }
+    let side = face.side.index();
+    let geo = chunk
+        .geometry_pal
+        .palette
+        .get(face.quad.data.geo_index.unwrap_or_default());
+    let on_edge = !geo.is_some_and(|geo| geo.is_rotated(face.quad.cube))
+        && if face.side.positive {
+            face.quad.end.2 == 16
+        } else {
+            face.quad.start.2 == 0
+        };
+    let normal = face.side.normal();
+    let layer = on_edge as i32;
+    let front = (
+        x as i32 + normal[0] as i32 * layer,
+        y as i32 + normal[1] as i32 * layer,
+        z as i32 + normal[2] as i32 * layer,
+    );
+    let sample = |(x, y, z): (i32, i32, i32)| -> Option<u8> {
+        let voxel =
+            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x as usize, y as usize, z as usize)];
+        let full = chunk
+            .geometry_pal
+            .palette
+            .get(voxel.geo_index.unwrap_or_default())
+            .is_none_or(|geo| geo.is_full_cube());
+        // Solid cubes have no light of their own and would only darken the face
+        if voxel.visibility == OPAQUE && full {
+            None
+        } else {
+            Some(light(&voxel).unwrap_or(16))
+        }
+    };
+    let ring = LIGHT_NEIGHBORS[side]
+        .map(|offset| sample((front.0 + offset.0, front.1 + offset.1, front.2 + offset.2)));
+    let own = Some(light(&face.quad.data).unwrap_or(16));
+    let center = if on_edge { sample(front).or(own) } else { own };
+    (center, ring)
 }
 
 pub struct FaceWithAO<'a> {
@@ -993,25 +1034,32 @@ pub(crate) fn side_aos(neighbors: [(RenderedBlockData, &BlockGeo); 8]) -> [u32;
     ]
 }
 
-pub(crate) fn side_light(neighbors: [u8; 8]) -> [f32; 4] {
-    [
-        (light_to_intern(neighbors[0])
-            + light_to_intern(neighbors[1])
-            + light_to_intern(neighbors[2]))
-            / 3.0,
-        (light_to_intern(neighbors[2])
-            + light_to_intern(neighbors[3])
-            + light_to_intern(neighbors[4]))
-            / 3.0,
-        (light_to_intern(neighbors[6])
-            + light_to_intern(neighbors[7])
-            + light_to_intern(neighbors[0]))
-            / 3.0,
-        (light_to_intern(neighbors[4])
-            + light_to_intern(neighbors[5])
-            + light_to_intern(neighbors[6]))
-            / 3.0,
-    ]
+/// Averages the center voxel with the two sides and corner touching each vertex. Voxels that are None block light
+/// and are left out. The corner is left out as well if both sides block since light can't reach it
+pub(crate) fn side_light(
+    center: Option<u8>,
+    neighbors: [Option<u8>; 8],
+    convert: impl Fn(u8) -> f32,
+) -> [f32; 4] {
+    [(0, 1, 2), (2, 3, 4), (6, 7, 0), (4, 5, 6)].map(|(side_one, corner, side_two)| {
+        let corner = if neighbors[side_one].is_none() && neighbors[side_two].is_none() {
+            None
+        } else {
+            neighbors[corner]
+        };
+        let samples = [center, neighbors[side_one], neighbors[side_two], corner];
+        let (total, count) = samples
+            .iter()
+            .flatten()
+            .fold((0.0, 0.0), |(total, count), light| {
+                (total + convert(*light), count + 1.0)
+            });
+        if count > 0.0 {
+            total / count
+        } else {
+            convert(0)
+        }
+    })
 }
 
 impl<'a> Deref for FaceWithAO<'a> {
@@ -1027,23 +1075,13 @@ impl<'a> Face<'a> {
         [start, start + 2, start + 1, start + 1, start + 2, start + 3]
     }
 
-    pub fn positions<
-        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
-        R: VoxRegistry<V> + Clone + Default,
-    >(
+    /// The four corners of the face in vertex order from its bounds along the two other axes of its side and along
+    /// its own axis
+    fn corners<T: Copy>(
         &self,
-        voxel_size: f32,
-        chunk: &ChunkBoundary<V, R>,
-    ) -> [[f32; 3]; 4] {
-        let (min_one, min_two, max_one, max_two, min_self, max_self) = (
-            (self.quad.start.0 as f32 / 16.0),
-            (self.quad.start.1 as f32 / 16.0),
-            (self.quad.end.0 as f32 / 16.0),
-            (self.quad.end.1 as f32 / 16.0),
-            (self.quad.start.2 as f32 / 16.0),
-            (self.quad.end.2 as f32 / 16.0),
-        );
-        let positions = match (&self.side.axis, &self.side.positive) {
+        (min_one, min_two, max_one, max_two, min_self, max_self): (T, T, T, T, T, T),
+    ) -> [[T; 3]; 4] {
+        match (&self.side.axis, &self.side.positive) {
             (Axis::X, false) => [
                 [min_self, min_one, max_two],
                 [min_self, min_one, min_two],
@@ -1080,7 +1118,119 @@ impl<'a> Face<'a> {
                 [max_one, max_two, max_self],
                 [min_one, max_two, max_self],
             ],
+        }
+    }
+
+    /// Positions of the four vertices in 1/16 of a voxel from the chunks origin for `VoxMesh::packed`. Built from the
+    /// quads bounds without going through floats. None for rotated geometry since packed uvs come from the position
+    /// along `TEXTURE_AXES`, or when a position doesn't fit in the 9 bits `PackedVertex` has per axis
+    pub fn packed_positions<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> Option<[[u32; 3]; 4]> {
+        let rotated = chunk
+            .geometry_pal
+            .palette
+            .get(self.quad.data.geo_index.unwrap_or_default())
+            .is_some_and(|geo| geo.is_rotated(self.quad.cube));
+        if rotated {
+            return None;
+        }
+        let (start, end, extent) = (self.quad.start, self.quad.end, self.quad.extent);
+        let corners = self.corners((
+            start.0 as i32,
+            start.1 as i32,
+            end.0 as i32 + (extent.0 as i32 - 1) * 16,
+            end.1 as i32 + (extent.1 as i32 - 1) * 16,
+            start.2 as i32,
+            end.2 as i32,
+        ));
+        let voxel = self.quad.voxel.map(|axis| (axis as i32 - 1) * 16);
+        let mut positions = [[0; 3]; 4];
+        for (position, corner) in positions.iter_mut().zip(corners) {
+            for axis in 0..3 {
+                position[axis] = u32::try_from(voxel[axis] + corner[axis])
+                    .ok()
+                    .filter(|position| *position <= PackedVertex::MAX_POSITION)?;
+            }
+        }
+        Some(positions)
+    }
+
+    /// Which of u (bit 0) and v (bit 1) the uvs of this face are flipped in for `VoxMesh::packed`. Packed uvs are
+    /// the position along the right and down `TEXTURE_AXES` of the side, so this is None when the cubes uv rect is
+    /// scaled from the face or offset from where the face sits in its voxel
+    pub fn packed_flips<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> Option<u8> {
+        let cube = chunk
+            .geometry_pal
+            .palette
+            .get(self.quad.data.geo_index.unwrap_or_default())?
+            .cubes
+            .get(self.quad.cube)?;
+        let face_index = self.side.index();
+        let ((offset_u, offset_v), (width, height)) = cube.uv[face_index];
+        let (right, up) = TEXTURE_AXES[face_index];
+        let (origin, end) = (
+            [cube.origin.0, cube.origin.1, cube.origin.2].map(i32::from),
+            [cube.end.0, cube.end.1, cube.end.2].map(i32::from),
+        );
+        // Lowest and highest position of the cube along a direction in pixels
+        let bounds = |direction: [i32; 3]| {
+            let axis = direction.iter().position(|axis| *axis != 0).unwrap();
+            if direction[axis] > 0 {
+                (origin[axis], end[axis])
+            } else {
+                (-end[axis], -origin[axis])
+            }
+        };
+        let ((min_right, max_right), (min_up, max_up)) = (bounds(right), bounds(up));
+        if i32::from(width).abs() != max_right - min_right
+            || i32::from(height).abs() != max_up - min_up
+        {
+            return None;
+        }
+        // The uvs are the position plus a constant which has to be a whole number of voxels. v grows downwards
+        let (offset_u, offset_v) = (i32::from(offset_u), i32::from(offset_v));
+        let shift_u = if width < 0 {
+            offset_u + min_right
+        } else {
+            offset_u - min_right
+        };
+        let shift_v = if height < 0 {
+            offset_v - max_up
+        } else {
+            offset_v + max_up
         };
+        (shift_u.rem_euclid(16) == 0 && shift_v.rem_euclid(16) == 0)
+            .then_some((width < 0) as u8 | ((height < 0) as u8) << 1)
+    }
+
+    pub fn positions<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        voxel_size: f32,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> [[f32; 3]; 4] {
+        let (min_one, min_two, max_one, max_two, min_self, max_self) = (
+            (self.quad.start.0 as f32 / 16.0),
+            (self.quad.start.1 as f32 / 16.0),
+            (self.quad.end.0 as f32 / 16.0 + self.quad.extent.0 as f32 - 1.0),
+            (self.quad.end.1 as f32 / 16.0 + self.quad.extent.1 as f32 - 1.0),
+            (self.quad.start.2 as f32 / 16.0),
+            (self.quad.end.2 as f32 / 16.0),
+        );
+        let positions = self.corners((min_one, min_two, max_one, max_two, min_self, max_self));
 
         let (x, y, z) = (
             (self.quad.voxel[0] - 1) as f32,
@@ -1126,7 +1276,7 @@ impl<'a> Face<'a> {
                 block_pivot.0 as f32 / 16.0 + x,
                 block_pivot.1 as f32 / 16.0 + y,
                 block_pivot.2 as f32 / 16.0 + z,
-            ); // TO emulate how itll be getting from geometry
+            ) * voxel_size; // TO emulate how itll be getting from geometry
             let rotation = glam::Quat::from_euler(
                 glam::EulerRot::XYZ,
                 (block_rotation.0 as f32).to_radians(),
@@ -1137,7 +1287,7 @@ impl<'a> Face<'a> {
                 cube_pivot.0 as f32 / 16.0 + x,
                 cube_pivot.1 as f32 / 16.0 + y,
                 cube_pivot.2 as f32 / 16.0 + z,
-            ); // TO emulate how itll be getting from geometry
+            ) * voxel_size; // TO emulate how itll be getting from geometry
             let rotation_cube = glam::Quat::from_euler(
                 glam::EulerRot::XYZ,
                 (cube_rotation.0 as f32).to_radians(),
@@ -1194,6 +1344,146 @@ impl<'a> Face<'a> {
         self.side.normals()
     }
 
+    /// Normals of the face with the block and cube rotation of its geometry applied. Falls back to `normals` when
+    /// the geometry isn't rotated or can't be found
+    pub fn rotated_normals<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> [[f32; 3]; 4] {
+        let Some((geo, cube)) = chunk
+            .geometry_pal
+            .palette
+            .get(self.quad.data.geo_index.unwrap_or_default())
+            .and_then(|geo| Some((geo, geo.cubes.get(self.quad.cube)?)))
+        else {
+            return self.normals();
+        };
+        if !geo.is_rotated(self.quad.cube) {
+            return self.normals();
+        }
+        let block_rotation = geo.rotation;
+        let cube_rotation = cube.rotation;
+        // Same rotations as `positions` without the pivots since normals are directions
+        let rotation = glam::Quat::from_euler(
+            glam::EulerRot::XYZ,
+            (cube_rotation.0 as f32).to_radians(),
+            (cube_rotation.1 as f32).to_radians(),
+            (cube_rotation.2 as f32).to_radians(),
+        ) * glam::Quat::from_euler(
+            glam::EulerRot::XYZ,
+            (block_rotation.0 as f32).to_radians(),
+            (block_rotation.1 as f32).to_radians(),
+            (block_rotation.2 as f32).to_radians(),
+        );
+        let normal = (rotation * glam::Vec3::from(self.side.normal())).into();
+        [normal; 4]
+    }
+
+    /// Which neighbors of this face it connects to (see `CONNECTION_OFFSETS`) for voxels with connected textures.
+    /// A neighbor connects when it has the same match index and connection mode and its face on this side isn't
+    /// covered by another voxel with the same match index
+    pub fn connections<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> Option<(ConnectedTextures, u8)> {
+        let mode = self.quad.data.connected?;
+        let face_index = self.side.index();
+        let (right, up) = TEXTURE_AXES[face_index];
+        // Faces with a negative uv size are drawn flipped
+        let ((_, _), (width, height)) = chunk
+            .geometry_pal
+            .palette
+            .get(self.quad.data.geo_index.unwrap_or_default())
+            .and_then(|geo| geo.cubes.get(self.quad.cube))
+            .map_or(((0, 0), (1, 1)), |cube| cube.uv[face_index]);
+        let flip = |size: i8| if size < 0 { -1 } else { 1 };
+        let (right, up) = (
+            right.map(|axis| axis * flip(width)),
+            up.map(|axis| axis * flip(height)),
+        );
+        let normal = self.side.normal().map(|axis| axis as i32);
+        let voxel = self.quad.voxel;
+        let at = |offset: [i32; 3]| {
+            let [x, y, z] = [0, 1, 2].map(|axis| (voxel[axis] as i32 + offset[axis]) as usize);
+            &chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y, z)]
+        };
+        let match_index = self.quad.data.match_index;
+        let mut mask = 0;
+        for (bit, (one, two)) in CONNECTION_OFFSETS.into_iter().enumerate() {
+            let offset = [0, 1, 2].map(|axis| right[axis] * one + up[axis] * two);
+            let neighbor = at(offset);
+            let front = at([0, 1, 2].map(|axis| offset[axis] + normal[axis]));
+            if neighbor.match_index == match_index
+                && neighbor.connected == Some(mode)
+                && front.match_index != match_index
+            {
+                mask |= 1 << bit;
+            }
+        }
+        Some((mode, mask))
+    }
+
+    /// The normalized atlas rect (min x, min y, max x, max y) of the texture for this face. Connected textures pick
+    /// the tile from `connected_tile` out of a sheet of tiles the size of the face
+    pub fn tile_rect<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        asset_registry: &AssetRegistry,
+        matched_ind: usize,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> Option<[f32; 4]> {
+        let textures = self.quad.data.textures?;
+        let texture_uv = textures[matched_ind];
+        let geo = chunk
+            .geometry_pal
+            .palette
+            .get(self.quad.data.geo_index.unwrap_or_default())
+            .unwrap();
+        let uv = geo.cubes.get(self.quad.cube).unwrap().uv;
+        let min_x = texture_uv.x;
+        let min_y = texture_uv.y;
+        let face_index = self.side.index();
+        let (tile_x, tile_y) = self
+            .connections(chunk)
+            .map_or((0, 0), |(mode, mask)| connected_tile(mode, mask));
+        let (min_x, min_y) = (
+            min_x
+                + uv.get(face_index).unwrap().0 .0 as f32
+                + tile_x as f32 * (uv.get(face_index).unwrap().1 .0 as f32).abs(),
+            min_y
+                + uv.get(face_index).unwrap().0 .1 as f32
+                + tile_y as f32 * (uv.get(face_index).unwrap().1 .1 as f32).abs(),
+        );
+        let (max_x, max_y) = (
+            min_x + uv.get(face_index).unwrap().1 .0 as f32,
+            min_y + uv.get(face_index).unwrap().1 .1 as f32,
+        );
+        Some([
+            min_x / asset_registry.texture_size.x,
+            min_y / asset_registry.texture_size.y,
+            max_x / asset_registry.texture_size.x,
+            max_y / asset_registry.texture_size.y,
+        ])
+    }
+
+    /// How many times the texture repeats along u and v. Only merged quads from greedy meshing repeat
+    fn uv_extent(&self) -> (f32, f32) {
+        let (one, two) = (self.quad.extent.0 as f32, self.quad.extent.1 as f32);
+        match (self.side.axis, self.side.positive) {
+            (Axis::X, _) | (Axis::Y, true) => (two, one),
+            (Axis::Y, false) | (Axis::Z, _) => (one, two),
+        }
+    }
+
+    /// Uvs of the four vertices with the texture variance of the default seed. See `seeded_uvs`
     pub fn uvs<
         V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
         R: VoxRegistry<V> + Clone + Default,
@@ -1201,155 +1491,418 @@ impl<'a> Face<'a> {
         &self,
         asset_registry: &AssetRegistry,
         matched_ind: usize,
-        _: mint::Vector3<i32>,
+        world_pos: mint::Vector3<i32>,
         chunk: &ChunkBoundary<V, R>,
     ) -> [[f32; 2]; 4] {
-        if let Some(textures) = self.quad.data.textures {
-            let texture_uv = textures[matched_ind];
-            let geo = chunk
-                .geometry_pal
-                .palette
-                .get(self.quad.data.geo_index.unwrap_or_default())
-                .unwrap();
-            let uv = geo.cubes.get(self.quad.cube).unwrap().uv;
-            let mut face_tex = [[0.0; 2]; 4];
-            let min_x = texture_uv.x;
-            let min_y = texture_uv.y;
-            let face_index = match (&self.side.axis, &self.side.positive) {
-                (Axis::X, false) => 0,
-                (Axis::X, true) => 1,
-                (Axis::Y, false) => 2,
-                (Axis::Y, true) => 3,
-                (Axis::Z, false) => 4,
-                (Axis::Z, true) => 5,
+        self.seeded_uvs(asset_registry, matched_ind, world_pos, 0, chunk)
+    }
+
+    /// Uvs of the four vertices. Faces of cubes with `FaceDescript::texture_variance` set are rotated and flipped by
+    /// `texture_variant` of the world position of their voxel and the seed unless they have connected textures
+    pub fn seeded_uvs<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        asset_registry: &AssetRegistry,
+        matched_ind: usize,
+        world_pos: mint::Vector3<i32>,
+        seed: u64,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> [[f32; 2]; 4] {
+        let Some(rect) = self.tile_rect(asset_registry, matched_ind, chunk) else {
+            return [[0.0, 0.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]];
+        };
+        self.rect_uvs(rect, world_pos, seed, chunk)
+    }
+
+    /// The texture array layer of this face for `TextureMode::Layers`. Connected textures use one layer per tile in
+    /// the order of their sheet starting at the faces layer
+    pub fn texture_layer<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        matched_ind: usize,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> Option<u32> {
+        let layer = self.quad.data.texture_layers?[matched_ind];
+        let tile = self.connections(chunk).map_or(0, |(mode, mask)| {
+            let (column, row) = connected_tile(mode, mask);
+            let columns = match mode {
+                ConnectedTextures::Full => 8,
+                ConnectedTextures::Edges => 4,
             };
-            let (min_x, min_y) = (
-                min_x + uv.get(face_index).unwrap().0 .0 as f32,
-                min_y + uv.get(face_index).unwrap().0 .1 as f32,
-            );
-            let (max_x, max_y) = (
-                min_x + uv.get(face_index).unwrap().1 .0 as f32,
-                min_y + uv.get(face_index).unwrap().1 .1 as f32,
-            );
-            let (min_x, min_y, max_x, max_y) = (
-                min_x / asset_registry.texture_size.x,
-                min_y / asset_registry.texture_size.y,
-                max_x / asset_registry.texture_size.x,
-                max_y / asset_registry.texture_size.y,
-            );
+            row * columns + column
+        });
+        Some(layer + tile)
+    }
+
+    /// Uvs of the four vertices inside their texture layer for `TextureMode::Layers`. A full face covers 0.0..1.0 and
+    /// merged quads go past 1.0 so the texture repeats with a repeating sampler. Texture variance and the flips from
+    /// the geometry are applied like in `seeded_uvs`
+    pub fn layer_uvs<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        world_pos: mint::Vector3<i32>,
+        seed: u64,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> [[f32; 2]; 4] {
+        let geo = chunk
+            .geometry_pal
+            .palette
+            .get(self.quad.data.geo_index.unwrap_or_default())
+            .unwrap();
+        let ((min_x, min_y), (width, height)) =
+            geo.cubes.get(self.quad.cube).unwrap().uv[self.side.index()];
+        let rect = [
+            min_x as f32 / 16.0,
+            min_y as f32 / 16.0,
+            (min_x + width) as f32 / 16.0,
+            (min_y + height) as f32 / 16.0,
+        ];
+        let mut uvs = self.rect_uvs(rect, world_pos, seed, chunk);
+        // Flipped faces count down from 1.0. Shift them by whole tiles so every uv is positive
+        for axis in 0..2 {
+            let shift = uvs
+                .iter()
+                .map(|uv| uv[axis])
+                .fold(f32::MAX, f32::min)
+                .floor()
+                .min(0.0);
+            for uv in uvs.iter_mut() {
+                uv[axis] -= shift;
+            }
+        }
+        uvs
+    }
 
-            face_tex[2][0] = min_x;
-            face_tex[2][1] = min_y;
-            face_tex[3][0] = max_x;
-            face_tex[3][1] = min_y;
-            face_tex[0][0] = min_x;
-            face_tex[0][1] = max_y;
-            face_tex[1][0] = max_x;
-            face_tex[1][1] = max_y;
-            // let flip_num = if self.quad.data.tex_variance[face_index] {
-            //     let mut rng: StdRng = SeedableRng::seed_from_u64(world_pos.reflect_hash().unwrap());
-            //     rng.gen_range(0..6)
-            // } else {
-            //     0
-            // };
-            // match flip_num {
-            //     0 => {
-            //         face_tex[2][0] = min_x;
-            //         face_tex[2][1] = min_y;
-            //         face_tex[3][0] = max_x;
-            //         face_tex[3][1] = min_y;
-            //         face_tex[0][0] = min_x;
-            //         face_tex[0][1] = max_y;
-            //         face_tex[1][0] = max_x;
-            //         face_tex[1][1] = max_y;
-            //     }
-            //     1 => {
-            //         face_tex[2][0] = max_x;
-            //         face_tex[2][1] = max_y;
-            //         face_tex[3][0] = min_x;
-            //         face_tex[3][1] = max_y;
-            //         face_tex[0][0] = max_x;
-            //         face_tex[0][1] = min_y;
-            //         face_tex[1][0] = min_x;
-            //         face_tex[1][1] = min_y;
-            //     }
-            //     2 => {
-            //         face_tex[2][0] = max_x;
-            //         face_tex[2][1] = min_y;
-            //         face_tex[3][0] = min_x;
-            //         face_tex[3][1] = min_y;
-            //         face_tex[0][0] = max_x;
-            //         face_tex[0][1] = max_y;
-            //         face_tex[1][0] = min_x;
-            //         face_tex[1][1] = max_y;
-            //     }
-            //     3 => {
-            //         face_tex[2][0] = min_x;
-            //         face_tex[2][1] = max_y;
-            //         face_tex[3][0] = max_x;
-            //         face_tex[3][1] = max_y;
-            //         face_tex[0][0] = min_x;
-            //         face_tex[0][1] = min_y;
-            //         face_tex[1][0] = max_x;
-            //         face_tex[1][1] = min_y;
-            //     }
-            //     4 => {
-            //         face_tex[2][0] = max_x;
-            //         face_tex[2][1] = max_y;
-            //         face_tex[3][0] = max_x;
-            //         face_tex[3][1] = min_y;
-            //         face_tex[0][0] = min_x;
-            //         face_tex[0][1] = max_y;
-            //         face_tex[1][0] = min_x;
-            //         face_tex[1][1] = min_y;
-            //     }
-            //     5 => {
-            //         face_tex[2][0] = min_x;
-            //         face_tex[2][1] = min_y;
-            //         face_tex[3][0] = min_x;
-            //         face_tex[3][1] = max_y;
-            //         face_tex[0][0] = max_x;
-            //         face_tex[0][1] = min_y;
-            //         face_tex[1][0] = max_x;
-            //         face_tex[1][1] = max_y;
-            //     }
-            //     _ => {}
-            // }
-            return face_tex;
+    /// The `texture_variant` of this face or 0 when its cube has no texture variance on this side or the voxel has
+    /// connected textures
+    pub fn texture_variant<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        world_pos: mint::Vector3<i32>,
+        seed: u64,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> u8 {
+        let face_index = self.side.index();
+        let variance = chunk
+            .geometry_pal
+            .palette
+            .get(self.quad.data.geo_index.unwrap_or_default())
+            .and_then(|geo| geo.cubes.get(self.quad.cube))
+            .is_some_and(|cube| cube.texture_variance[face_index]);
+        if variance && self.quad.data.connected.is_none() {
+            texture_variant(world_pos, face_index, seed)
+        } else {
+            0
         }
-        [[0.0, 0.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]]
+    }
+
+    /// Spreads the corners of a texture rect (min x, min y, max x, max y) over the vertices
+    fn rect_uvs<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        &self,
+        [min_x, min_y, max_x, max_y]: [f32; 4],
+        world_pos: mint::Vector3<i32>,
+        seed: u64,
+        chunk: &ChunkBoundary<V, R>,
+    ) -> [[f32; 2]; 4] {
+        let variant = self.texture_variant(world_pos, seed, chunk);
+        // Merged quads go past the tile. Atlas shaders wrap them back into it using `VoxMesh::uv_rects`
+        let (u_extent, v_extent) = self.uv_extent();
+        // Vertex corners in tiles
+        [
+            [0.0, v_extent],
+            [u_extent, v_extent],
+            [0.0, 0.0],
+            [u_extent, 0.0],
+        ]
+        .map(|[u, v]| {
+            let (u, v, u_extent, v_extent) = if variant & 4 != 0 {
+                (v, u, v_extent, u_extent)
+            } else {
+                (u, v, u_extent, v_extent)
+            };
+            let u = if variant & 1 != 0 { u_extent - u } else { u };
+            let v = if variant & 2 != 0 { v_extent - v } else { v };
+            [min_x + (max_x - min_x) * u, min_y + (max_y - min_y) * v]
+        })
     }
 
     pub fn voxel(&self) -> [usize; 3] {
         self.quad.voxel
     }
+
+    /// World position of the voxel this face belongs to in a chunk at chunk_pos whose voxels are scale voxels wide.
+    /// For LOD chunks chunk_pos is `lod_origin` and scale is `lod_scale`
+    pub fn world_pos(&self, chunk_pos: mint::Vector3<i32>, scale: i32) -> VoxelPos {
+        let [x, y, z] = self.voxel().map(|axis| (axis as i32 - 1) * scale);
+        VoxelPos::new(
+            chunk_pos.x * CHUNK_SIZE as i32 + x,
+            chunk_pos.y * CHUNK_SIZE as i32 + y,
+            chunk_pos.z * CHUNK_SIZE as i32 + z,
+        )
+    }
 }
 
-// Possibly have this just fully generate the mesh
-pub fn generate_mesh<
-    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
-    R: VoxRegistry<V> + Clone + Default,
->(
-    chunk: &ChunkBoundary<V, R>,
-    solid_pass: bool,
-    buffer: &mut QuadGroups,
-) {
-    buffer.clear();
-    for z in 1..ChunkBoundary::<V, R>::edge() - 1 {
-        for y in 1..ChunkBoundary::<V, R>::edge() - 1 {
-            for x in 1..ChunkBoundary::<V, R>::edge() - 1 {
-                let voxel = chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y, z)];
-                match voxel.visibility {
-                    EMPTY => continue,
-                    visibility => {
-                        let neighbor_block = [
-                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y, z)],
-                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y, z)],
-                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y - 1, z)],
-                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y + 1, z)],
-                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y, z - 1)],
+/// One bit per voxel along x for every (y, z) row of a `ChunkBoundary`
+struct RowMasks {
+    /// Opaque full cubes that cull and block every face. These can be culled against each other with bitwise ops
+    plain: Vec<u32>,
+    empty: Vec<u32>,
+}
+
+impl RowMasks {
+    fn new<
+        V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+        R: VoxRegistry<V> + Clone + Default,
+    >(
+        chunk: &ChunkBoundary<V, R>,
+    ) -> Self {
+        let edge = ChunkBoundary::<V, R>::edge();
+        let plain_geo = chunk
+            .geometry_pal
+            .palette
+            .iter()
+            .map(|geo| {
+                geo.is_full_cube()
+                    && geo.cubes[0].cull.iter().all(|cull| *cull)
+                    && !geo.cubes[0].discard.iter().any(|discard| *discard)
+            })
+            .collect::<Vec<_>>();
+        let mut masks = RowMasks {
+            plain: vec![0; edge * edge],
+            empty: vec![0; edge * edge],
+        };
+        for z in 0..edge {
+            for y in 0..edge {
+                let row = Self::row(edge, y, z);
+                for x in 0..edge {
+                    let voxel = &chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y, z)];
+                    match voxel.visibility {
+                        EMPTY => masks.empty[row] |= 1 << x,
+                        OPAQUE
+                            if !voxel.smooth
+                                && voxel.blocks.iter().all(|blocks| *blocks)
+                                && plain_geo
+                                    .get(voxel.geo_index.unwrap_or_default())
+                                    .is_some_and(|plain| *plain) =>
+                        {
+                            masks.plain[row] |= 1 << x
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        masks
+    }
+
+    fn row(edge: usize, y: usize, z: usize) -> usize {
+        z * edge + y
+    }
+
+    /// Bits of the faces of plain cubes in a row that are hidden by a plain neighbor, in side order
+    fn hidden(&self, edge: usize, y: usize, z: usize) -> [u32; 6] {
+        let plain = self.plain[Self::row(edge, y, z)];
+        [
+            plain & (plain << 1),
+            plain & (plain >> 1),
+            plain & self.plain[Self::row(edge, y - 1, z)],
+            plain & self.plain[Self::row(edge, y + 1, z)],
+            plain & self.plain[Self::row(edge, y, z - 1)],
+            plain & self.plain[Self::row(edge, y, z + 1)],
+        ]
+    }
+}
+
+/// One of the 8 ways to rotate and flip a faces texture. Bit 0 flips u, bit 1 flips v and bit 2 swaps them.
+/// Only depends on its inputs so meshes come out the same on every run and machine
+pub fn texture_variant(world_pos: mint::Vector3<i32>, side: usize, seed: u64) -> u8 {
+    let hash = splitmix64(seed ^ (world_pos.x as u32 as u64 | (world_pos.y as u32 as u64) << 32));
+    let hash = splitmix64(hash ^ (world_pos.z as u32 as u64 | (side as u64) << 32));
+    (hash >> 61) as u8
+}
+
+/// Picks one of the weighted variants of a voxel from its world position. None when there are no variants or all of
+/// them have a weight of 0. Like `texture_variant` this only depends on its inputs
+pub fn pick_variant(world_pos: mint::Vector3<i32>, weights: &[u32]) -> Option<usize> {
+    let total = weights.iter().map(|weight| *weight as u64).sum::<u64>();
+    if total == 0 {
+        return None;
+    }
+    // Salted so the picked variant doesn't follow the texture rotation of the same voxel
+    let hash = splitmix64(
+        0x5641_5249_414E_5453 ^ (world_pos.x as u32 as u64 | (world_pos.y as u32 as u64) << 32),
+    );
+    let mut roll = splitmix64(hash ^ world_pos.z as u32 as u64) % total;
+    weights.iter().position(|weight| {
+        let picked = roll < *weight as u64;
+        roll = roll.saturating_sub(*weight as u64);
+        picked
+    })
+}
+
+/// Offsets (right, up) in texture space of the neighbors in a connection mask, clockwise from up. Bit n of a mask is
+/// set when the neighbor at `CONNECTION_OFFSETS[n]` connects
+pub const CONNECTION_OFFSETS: [(i32, i32); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// World directions of texture right and up on every side in side order for faces with a positive uv size. Matches
+/// the uvs from `Face::seeded_uvs`
+pub const TEXTURE_AXES: [([i32; 3], [i32; 3]); 6] = [
+    ([0, 0, -1], [0, 1, 0]),
+    ([0, 0, 1], [0, 1, 0]),
+    ([1, 0, 0], [0, 0, -1]),
+    ([0, 0, -1], [1, 0, 0]),
+    ([1, 0, 0], [0, 1, 0]),
+    ([-1, 0, 0], [0, 1, 0]),
+];
+
+/// Drops the corners of a connection mask whose edges don't both connect since they can't change the tile
+const fn reduce_mask(mask: u8) -> u8 {
+    let mut reduced = mask & 0b0101_0101;
+    let mut corner = 1;
+    while corner < 8 {
+        let edges = 1 << (corner - 1) | 1 << ((corner + 1) % 8);
+        if mask & (1 << corner) != 0 && mask & edges == edges {
+            reduced |= 1 << corner;
+        }
+        corner += 2;
+    }
+    reduced
+}
+
+/// Tile of every connection mask in a `ConnectedTextures::Full` sheet
+const FULL_TILES: [u8; 256] = {
+    let mut tiles = [0; 256];
+    let mut mask = 0;
+    while mask < 256 {
+        let reduced = reduce_mask(mask as u8);
+        let mut lower = 0;
+        while lower < reduced {
+            if reduce_mask(lower) == lower {
+                tiles[mask] += 1;
+            }
+            lower += 1;
+        }
+        mask += 1;
+    }
+    tiles
+};
+
+/// Column and row in the sheet of tiles of the tile for a mask of connected neighbors (see `CONNECTION_OFFSETS`).
+/// Full sheets hold the 47 masks left after dropping corners whose edges don't both connect in ascending order, 8 to a
+/// row. Edge sheets use the up, right, down and left bits as a 4 bit index, 4 to a row
+pub fn connected_tile(mode: ConnectedTextures, mask: u8) -> (u32, u32) {
+    match mode {
+        ConnectedTextures::Full => {
+            let tile = FULL_TILES[mask as usize] as u32;
+            (tile % 8, tile / 8)
+        }
+        ConnectedTextures::Edges => {
+            let tile = (0..4)
+                .map(|edge| (mask as u32 >> (edge * 2) & 1) << edge)
+                .sum::<u32>();
+            (tile % 4, tile / 4)
+        }
+    }
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+// Possibly have this just fully generate the mesh
+pub fn generate_mesh<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    chunk: &ChunkBoundary<V, R>,
+    solid_pass: bool,
+    buffer: &mut QuadGroups,
+) {
+    let pass = if solid_pass {
+        MeshPass::Solid
+    } else {
+        MeshPass::Transparent
+    };
+    generate_mesh_pass(chunk, pass, buffer);
+}
+
+/// Finds the visible faces of every voxel that is drawn in the given pass
+pub fn generate_mesh_pass<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    chunk: &ChunkBoundary<V, R>,
+    pass: MeshPass,
+    buffer: &mut QuadGroups,
+) {
+    buffer.clear();
+    let edge = ChunkBoundary::<V, R>::edge();
+    let masks = RowMasks::new(chunk);
+    let inner = ((1 << (edge - 1)) - 1) & !1;
+    for z in 1..edge - 1 {
+        for y in 1..edge - 1 {
+            let row = RowMasks::row(edge, y, z);
+            if masks.empty[row] & inner == inner {
+                continue;
+            }
+            let hidden = masks.hidden(edge, y, z);
+            // Plain cubes hidden on every side are skipped without looking at their neighbors
+            let buried = hidden
+                .iter()
+                .fold(u32::MAX, |buried, hidden| buried & hidden);
+            for x in 1..edge - 1 {
+                if buried & (1 << x) != 0 {
+                    continue;
+                }
+                let voxel = chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y, z)];
+                match (pass, voxel.visibility) {
+                    (_, EMPTY) => continue,
+                    // Meshed by `smooth_mesh`
+                    _ if voxel.smooth => continue,
+                    // Opaque and cutout voxels only end up in their own mesh
+                    (MeshPass::Cutout | MeshPass::Transparent, OPAQUE)
+                    | (MeshPass::Solid | MeshPass::Transparent, CUTOUT)
+                    | (MeshPass::Cutout, TRANSPARENT) => continue,
+                    (_, visibility) => {
+                        let neighbor_block = [
+                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x - 1, y, z)],
+                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x + 1, y, z)],
+                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y - 1, z)],
+                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y + 1, z)],
+                            chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y, z - 1)],
                             chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y, z + 1)],
-                        ];
+                        ]
+                        // The smooth surface doesn't line up with block faces so it never hides them
+                        .map(|neighbor| {
+                            if neighbor.smooth {
+                                RenderedBlockData::default()
+                            } else {
+                                neighbor
+                            }
+                        });
                         let geo = chunk
                             .geometry_pal
                             .palette
@@ -1358,7 +1911,7 @@ pub fn generate_mesh<
                         for (cube_num, cube) in geo.cubes.iter().enumerate() {
                             for (i, neighbor) in neighbor_block.iter().enumerate() {
                                 let culled = cube.cull[i];
-                                if cube.discard[i] {
+                                if cube.discard[i] || hidden[i] & (1 << x) != 0 {
                                     continue;
                                 }
                                 let blocked = match i {
@@ -1389,30 +1942,47 @@ pub fn generate_mesh<
                                     && (blocked
                                         || (neighbor.geo_index == voxel.geo_index && blocked_self))
                                 {
-                                    if solid_pass {
-                                        match (visibility, other) {
-                                            (OPAQUE, EMPTY) | (OPAQUE, TRANSPARENT) => true,
+                                    match pass {
+                                        MeshPass::Solid => match (visibility, other) {
+                                            (OPAQUE, EMPTY)
+                                            | (OPAQUE, TRANSPARENT)
+                                            | (OPAQUE, CUTOUT) => true,
 
                                             (TRANSPARENT, TRANSPARENT) => {
                                                 voxel.match_index != neighbor.match_index
                                             }
 
                                             (_, _) => false,
-                                        }
-                                    } else {
-                                        match (visibility, other) {
-                                            (TRANSPARENT, EMPTY) => true,
+                                        },
+                                        // Faces between two of the same cutout voxel are culled like
+                                        // transparent ones
+                                        MeshPass::Cutout => match (visibility, other) {
+                                            (CUTOUT, EMPTY) | (CUTOUT, TRANSPARENT) => true,
+
+                                            (CUTOUT, CUTOUT) => {
+                                                voxel.match_index != neighbor.match_index
+                                            }
+
+                                            (_, _) => false,
+                                        },
+                                        MeshPass::Transparent => match (visibility, other) {
+                                            (TRANSPARENT, EMPTY) | (TRANSPARENT, CUTOUT) => true,
 
                                             (TRANSPARENT, TRANSPARENT) => {
                                                 voxel.match_index != neighbor.match_index
                                             }
 
                                             (_, _) => false,
-                                        }
+                                        },
                                     }
                                 } else {
-                                    (visibility == OPAQUE && solid_pass)
-                                        || (visibility == TRANSPARENT && !solid_pass) && !blocked
+                                    match pass {
+                                        MeshPass::Solid => visibility == OPAQUE,
+                                        MeshPass::Cutout => visibility == CUTOUT,
+                                        MeshPass::Transparent => {
+                                            visibility == TRANSPARENT && !blocked
+                                        }
+                                    }
                                 };
                                 let origin_one = match i {
                                     0 => cube.origin.1,
@@ -1476,6 +2046,7 @@ pub fn generate_mesh<
                                         end: (end_one, end_two, self_end),
                                         cube: cube_num,
                                         data: voxel,
+                                        extent: (1, 1),
                                     });
                                 }
                             }
@@ -1489,6 +2060,53 @@ pub fn generate_mesh<
     // buffer
 }
 
+/// How light ends up in a meshes vertex attributes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LightMode {
+    /// Light and AO are multiplied into `VoxMesh::colors`. The mesh has to be rebuilt when light should look different
+    #[default]
+    Baked,
+    /// Sky light, block light and AO are stored raw in their own attributes (0.0..1.0) so a shader can apply time of
+    /// day and its own light curve. `VoxMesh::colors` is left empty unless colored light is used
+    Separate,
+}
+
+/// How faces address their textures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureMode {
+    /// Uvs point into the texture atlas using `AssetRegistry::texture_uvs`
+    #[default]
+    Atlas,
+    /// Every face gets its layer of a texture array from `AssetRegistry::texture_layers` in `VoxMesh::texture_layers`
+    /// and uvs inside that layer. Avoids bleeding between atlas tiles and merged quads repeat without `uv_rects`
+    Layers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MeshOptions {
+    pub light_mode: LightMode,
+    /// Use the red, green and blue light from the colored light mode instead of the single block light channel.
+    /// Baked meshes get tinted colors and separate meshes get the raw colored light in `VoxMesh::colors`
+    pub colored_light: bool,
+    /// Merge faces of full cubes that look the same into bigger quads. See `greedy_merge`
+    pub greedy: bool,
+    /// Level of detail of the chunk being meshed. Every voxel is `lod_scale(lod)` meters wide. See `lod_mesh`
+    pub lod: u8,
+    /// Keep the faces of each side together and record where they are in `VoxMesh::side_ranges` so renderers can
+    /// skip sides facing away from the camera or draw them separately
+    pub side_buckets: bool,
+    /// Output `VoxMesh::tangents` for normal mapped textures
+    pub tangents: bool,
+    /// Seed for `texture_variant`. Changing it changes the texture rotation of every face with texture variance
+    pub variance_seed: u64,
+    pub texture_mode: TextureMode,
+    /// Output `VoxMesh::packed` instead of the float attributes to save memory on far away chunks. See `PackedVertex`.
+    /// Only works with `TextureMode::Layers`. Meshes in atlas mode or with faces the packed format can't hold
+    /// (rotated geometry, uv rects scaled or offset from the face and layers above `u16::MAX`) are built with float
+    /// attributes and `VoxMesh::packed` left None
+    pub packed: bool,
+}
+
 pub fn full_mesh<
     V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
     R: VoxRegistry<V> + Clone + Default,
@@ -1497,130 +2115,274 @@ pub fn full_mesh<
     raw_chunk: &ChunkBoundary<V, R>,
     chunk_pos: mint::Vector3<i32>,
 ) -> MeshedChunk {
-    let mut buffer = QuadGroups::default();
-    generate_mesh(raw_chunk, true, &mut buffer);
-    let mut positions = Vec::new();
-    let mut indices = Vec::new();
-    let mut normals = Vec::new();
-    let mut uvs = Vec::new();
-    let mut ao = Vec::new();
-    let mut light = Vec::new();
-    for face in buffer.iter_with_ao(raw_chunk) {
-        indices.extend_from_slice(&face.indices(positions.len() as u32));
-        positions.extend_from_slice(&face.positions(1.0, raw_chunk)); // Voxel size is 1m
-        normals.extend_from_slice(&face.normals());
-        ao.extend_from_slice(&face.aos());
-        light.extend_from_slice(&face.light());
-        let matched_index = match (face.side.axis, face.side.positive) {
-            (Axis::X, false) => 2,
-            (Axis::X, true) => 3,
-            (Axis::Y, false) => 1,
-            (Axis::Y, true) => 0,
-            (Axis::Z, false) => 5,
-            (Axis::Z, true) => 4,
-        };
-        // let _matched_neighbor = match (face.side.axis, face.side.positive) {
-        //     (Axis::X, false) => (face.voxel()[0] - 1, face.voxel()[1], face.voxel()[2]),
-        //     (Axis::X, true) => (face.voxel()[0] + 1, face.voxel()[1], face.voxel()[2]),
-        //     (Axis::Y, false) => (face.voxel()[0], face.voxel()[1] - 1, face.voxel()[2]),
-        //     (Axis::Y, true) => (face.voxel()[0], face.voxel()[1] + 1, face.voxel()[2]),
-        //     (Axis::Z, false) => (face.voxel()[0], face.voxel()[1], face.voxel()[2] - 1),
-        //     (Axis::Z, true) => (face.voxel()[0], face.voxel()[1], face.voxel()[2] + 1),
-        // };
-
-        uvs.extend_from_slice(
-            &face.uvs(
-                asset_registry,
-                matched_index,
-                VoxelPos::from(
-                    mint::Vector3::<f32>::from(glam::Vec3::new(
-                        face.voxel()[0] as f32,
-                        face.voxel()[1] as f32,
-                        face.voxel()[2] as f32,
-                    )), // .into(),
-                )
-                .into(),
-                // .as_vec3()
-                // .into(),
-                raw_chunk,
-            ),
-        );
-    }
-    let final_ao = ao_convert(ao);
-    let mut final_color = Vec::new();
-    for (idx, color) in final_ao.iter().enumerate() {
-        final_color.extend_from_slice(&[[
-            color[0] * light[idx],
-            color[1] * light[idx],
-            color[2] * light[idx],
-            color[3],
-        ]]);
-    }
-
-    let mesh = VoxMesh {
-        vertices: positions.clone(),
-        normals: normals.clone(),
-        colors: Some(final_color.clone()),
-        indices: indices.clone(),
-        uvs: Some(uvs.clone()),
-    };
+    full_mesh_with_options(
+        asset_registry,
+        raw_chunk,
+        chunk_pos,
+        &MeshOptions::default(),
+    )
+}
 
-    buffer.clear();
+pub fn full_mesh_with_options<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    asset_registry: &AssetRegistry,
+    raw_chunk: &ChunkBoundary<V, R>,
+    chunk_pos: mint::Vector3<i32>,
+    options: &MeshOptions,
+) -> MeshedChunk {
+    full_mesh_with_buffers(
+        asset_registry,
+        raw_chunk,
+        chunk_pos,
+        options,
+        &mut MeshBuffers::default(),
+    )
+}
 
-    //Transparent Mesh
-    generate_mesh(raw_chunk, false, &mut buffer);
-    let mut ao = Vec::new();
-    let mut positions = Vec::new();
-    let mut indices = Vec::new();
-    let mut normals = Vec::new();
-    let mut uvs = Vec::new();
-    for face in buffer.iter_with_ao(raw_chunk) {
-        indices.extend_from_slice(&face.indices(positions.len() as u32));
+/// Scratch space used while meshing. Keep one around per thread to avoid reallocating it for every chunk
+#[derive(Default)]
+pub struct MeshBuffers {
+    quads: QuadGroups,
+    ao: Vec<u32>,
+    light: Vec<[f32; 3]>,
+}
 
-        positions.extend_from_slice(&face.positions(1.0, raw_chunk)); // Voxel size is 1m
-        normals.extend_from_slice(&face.normals());
-        ao.extend_from_slice(&face.aos());
-        let matched_index = match (face.side.axis, face.side.positive) {
-            (Axis::X, false) => 2,
-            (Axis::X, true) => 3,
-            (Axis::Y, false) => 1,
-            (Axis::Y, true) => 0,
-            (Axis::Z, false) => 5,
-            (Axis::Z, true) => 4,
-        };
+/// Same as `full_mesh_with_options` but reuses the given buffers
+pub fn full_mesh_with_buffers<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    asset_registry: &AssetRegistry,
+    raw_chunk: &ChunkBoundary<V, R>,
+    chunk_pos: mint::Vector3<i32>,
+    options: &MeshOptions,
+    buffers: &mut MeshBuffers,
+) -> MeshedChunk {
+    generate_mesh_pass(raw_chunk, MeshPass::Solid, &mut buffers.quads);
+    if options.greedy {
+        greedy_merge(raw_chunk, &mut buffers.quads, options);
+    }
+    let mesh = build_mesh(asset_registry, raw_chunk, chunk_pos, buffers, options);
 
-        uvs.extend_from_slice(
-            &face.uvs(
-                asset_registry,
-                matched_index,
-                VoxelPos::from(
-                    mint::Vector3::<f32>::from(glam::Vec3::new(
-                        face.voxel()[0] as f32,
-                        face.voxel()[1] as f32,
-                        face.voxel()[2] as f32,
-                    )), // .into(),
-                )
-                .into(),
-                raw_chunk,
-            ),
-        );
+    //Cutout Mesh
+    generate_mesh_pass(raw_chunk, MeshPass::Cutout, &mut buffers.quads);
+    if options.greedy {
+        greedy_merge(raw_chunk, &mut buffers.quads, options);
     }
+    let cutout_mesh = build_mesh(asset_registry, raw_chunk, chunk_pos, buffers, options);
 
-    let transparent_mesh = VoxMesh {
-        vertices: positions.clone(),
-        normals: normals.clone(),
-        colors: Some(final_color.clone()),
-        indices: indices.clone(),
-        uvs: Some(uvs.clone()),
+    //Transparent Mesh
+    generate_mesh_pass(raw_chunk, MeshPass::Transparent, &mut buffers.quads);
+    if options.greedy {
+        greedy_merge(raw_chunk, &mut buffers.quads, options);
+    }
+    let mut transparent_mesh = build_mesh(asset_registry, raw_chunk, chunk_pos, buffers, options);
+    // Kept so the transparent faces can be sorted back to front later
+    let face_centers = |vertices: &[[f32; 3]]| {
+        vertices
+            .chunks_exact(4)
+            .map(|face| {
+                (face
+                    .iter()
+                    .map(|vertex| glam::Vec3::from(*vertex))
+                    .sum::<glam::Vec3>()
+                    / 4.0)
+                    .into()
+            })
+            .collect()
     };
+    transparent_mesh.face_centers = Some(match &transparent_mesh.packed {
+        Some(packed) => {
+            let voxel_size = lod_scale(options.lod) as f32;
+            face_centers(
+                &packed
+                    .iter()
+                    .map(|vertex| {
+                        PackedVertex::unpack(*vertex)
+                            .position
+                            .map(|axis| axis as f32 / 16.0 * voxel_size)
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        }
+        None => face_centers(&transparent_mesh.vertices),
+    });
 
     MeshedChunk {
         chunk_mesh: mesh,
+        cutout_mesh,
         transparent_mesh,
+        smooth_mesh: smooth_mesh(raw_chunk, options),
         pos: ChunkPos(chunk_pos),
     }
 }
 
+fn build_mesh<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    asset_registry: &AssetRegistry,
+    raw_chunk: &ChunkBoundary<V, R>,
+    chunk_pos: mint::Vector3<i32>,
+    buffers: &mut MeshBuffers,
+    options: &MeshOptions,
+) -> VoxMesh {
+    if options.packed {
+        if let Some(mesh) = packed_mesh(raw_chunk, chunk_pos, &buffers.quads, options) {
+            return mesh;
+        }
+    }
+    let MeshBuffers { quads, ao, light } = buffers;
+    let voxel_size = lod_scale(options.lod) as f32;
+    ao.clear();
+    light.clear();
+    let vertex_count = quads.groups.iter().map(|quads| quads.len()).sum::<usize>() * 4;
+    let mut positions = Vec::with_capacity(vertex_count);
+    let mut indices = Vec::with_capacity(vertex_count / 4 * 6);
+    let mut normals = Vec::with_capacity(vertex_count);
+    let mut uvs = Vec::with_capacity(vertex_count);
+    let mut sky_light = Vec::new();
+    let mut block_light = Vec::new();
+    let mut colored_light = Vec::new();
+    let mut emissive = Vec::new();
+    let mut uv_rects = Vec::new();
+    let mut tangents = Vec::new();
+    let mut texture_layers = Vec::new();
+    for face in quads.iter_with_ao(raw_chunk) {
+        let emission = face.quad.data.emission;
+        indices.extend_from_slice(&face.indices(positions.len() as u32));
+        positions.extend_from_slice(&face.positions(voxel_size, raw_chunk));
+        normals.extend_from_slice(&face.rotated_normals(raw_chunk));
+        ao.extend_from_slice(&face.aos());
+        match options.light_mode {
+            LightMode::Baked => {
+                let face_light = if options.colored_light {
+                    face_colored_lights(&face, raw_chunk)
+                } else {
+                    face.light().map(|light| [light; 3])
+                };
+                // Voxels that give off light are at least as bright as their own light
+                let emitted = if emission > 0 {
+                    light_to_intern(emission)
+                } else {
+                    0.0
+                };
+                light.extend(face_light.map(|color| color.map(|light| light.max(emitted))));
+            }
+            LightMode::Separate => {
+                if options.colored_light {
+                    let colors = LightChannel::COLORS
+                        .map(|channel| face_light_levels(&face, raw_chunk, channel));
+                    colored_light.extend([0, 1, 2, 3].map(|vertex| {
+                        [colors[0][vertex], colors[1][vertex], colors[2][vertex], 1.0]
+                    }));
+                }
+                sky_light.extend_from_slice(&face_light_levels(
+                    &face,
+                    raw_chunk,
+                    LightChannel::Sky,
+                ));
+                block_light.extend_from_slice(&face_light_levels(
+                    &face,
+                    raw_chunk,
+                    LightChannel::Block,
+                ));
+            }
+        }
+        emissive.extend_from_slice(&[emission as f32 / MAX_LIGHT as f32; 4]);
+        let matched_index = face.side.texture_index();
+
+        let world_pos = face.world_pos(chunk_pos, lod_scale(options.lod));
+        match options.texture_mode {
+            TextureMode::Atlas => {
+                uvs.extend_from_slice(&face.seeded_uvs(
+                    asset_registry,
+                    matched_index,
+                    world_pos.into(),
+                    options.variance_seed,
+                    raw_chunk,
+                ));
+                if options.greedy {
+                    let rect = face
+                        .tile_rect(asset_registry, matched_index, raw_chunk)
+                        .unwrap_or_default();
+                    uv_rects.extend_from_slice(&[rect; 4]);
+                }
+            }
+            TextureMode::Layers => {
+                uvs.extend_from_slice(&face.layer_uvs(
+                    world_pos.into(),
+                    options.variance_seed,
+                    raw_chunk,
+                ));
+                let layer = face
+                    .texture_layer(matched_index, raw_chunk)
+                    .unwrap_or_default();
+                texture_layers.extend_from_slice(&[layer; 4]);
+            }
+        }
+        if options.tangents {
+            let start = positions.len() - 4;
+            tangents.extend_from_slice(&face_tangents(
+                &positions[start..],
+                &uvs[start..],
+                normals[start],
+            ));
+        }
+    }
+    let uv_rects =
+        (options.greedy && options.texture_mode == TextureMode::Atlas).then_some(uv_rects);
+    let tangents = options.tangents.then_some(tangents);
+    let texture_layers = (options.texture_mode == TextureMode::Layers).then_some(texture_layers);
+    let side_ranges = options.side_buckets.then(|| side_ranges(quads));
+
+    match options.light_mode {
+        LightMode::Baked => {
+            let final_ao = ao_convert(ao);
+            let mut final_color = Vec::with_capacity(vertex_count);
+            for (idx, color) in final_ao.iter().enumerate() {
+                final_color.extend_from_slice(&[[
+                    color[0] * light[idx][0],
+                    color[1] * light[idx][1],
+                    color[2] * light[idx][2],
+                    color[3],
+                ]]);
+            }
+            VoxMesh {
+                vertices: positions,
+                normals,
+                colors: Some(final_color),
+                indices,
+                uvs: Some(uvs),
+                emissive: Some(emissive),
+                uv_rects,
+                side_ranges,
+                tangents,
+                texture_layers,
+                ..Default::default()
+            }
+        }
+        LightMode::Separate => VoxMesh {
+            vertices: positions,
+            colors: options.colored_light.then_some(colored_light),
+            normals,
+            indices,
+            uvs: Some(uvs),
+            sky_light: Some(sky_light),
+            block_light: Some(block_light),
+            ao: Some(ao.iter().map(|ao| *ao as f32 / 3.0).collect()),
+            emissive: Some(emissive),
+            uv_rects,
+            side_ranges,
+            tangents,
+            texture_layers,
+            ..Default::default()
+        },
+    }
+}
+
 #[derive(Default)]
 pub struct VoxMesh {
     pub vertices: Vec<[f32; 3]>,
@@ -1628,14 +2390,103 @@ pub struct VoxMesh {
     pub indices: Vec<u32>,
     pub colors: Option<Vec<[f32; 4]>>,
     pub uvs: Option<Vec<[f32; 2]>>,
+    /// Raw sky light per vertex (0.0..1.0) when meshed with `LightMode::Separate`
+    pub sky_light: Option<Vec<f32>>,
+    /// Raw block light per vertex (0.0..1.0) when meshed with `LightMode::Separate`
+    pub block_light: Option<Vec<f32>>,
+    /// Ambient occlusion per vertex (0.0 fully occluded..1.0 open) when meshed with `LightMode::Separate`
+    pub ao: Option<Vec<f32>>,
+    /// How much light the voxel a vertex belongs to gives off (0.0..1.0) so shaders can keep it bright at night
+    pub emissive: Option<Vec<f32>>,
+    /// The atlas tile (start x, start y, end x, end y) of every vertex when greedy meshing. The end can be smaller than
+    /// the start when the geometry flips the texture. Merged quads have uvs that go past their tile so shaders should
+    /// wrap them back with `rect.xy + fract((uv - rect.xy) / (rect.zw - rect.xy)) * (rect.zw - rect.xy)`
+    pub uv_rects: Option<Vec<[f32; 4]>>,
+    /// Center of every face (every 4 vertices). Only kept for the transparent mesh so it can be sorted with `sort_faces`
+    pub face_centers: Option<Vec<[f32; 3]>>,
+    /// The range of `indices` of each side in side order when meshed with `MeshOptions::side_buckets`
+    pub side_ranges: Option<[Range<usize>; 6]>,
+    /// Tangent of every vertex pointing along the textures u axis when meshed with `MeshOptions::tangents`. w is the
+    /// handedness so the bitangent is `cross(normal, tangent.xyz) * tangent.w`
+    pub tangents: Option<Vec<[f32; 4]>>,
+    /// Texture array layer of every vertex when meshed with `TextureMode::Layers`
+    pub texture_layers: Option<Vec<u32>>,
+    /// Every vertex as a `PackedVertex` when meshed with `MeshOptions::packed`. Only `indices` and `side_ranges` are
+    /// filled in next to it
+    pub packed: Option<Vec<[u32; 2]>>,
+}
+
+impl VoxMesh {
+    /// Reorders the faces in `indices` from furthest to closest to the camera so transparent faces blend correctly.
+    /// The camera is relative to the meshes origin. Vertices are left alone so this is cheap enough to call whenever
+    /// the camera moves. All faces are sorted together so `side_ranges` no longer hold and are cleared, use
+    /// `sort_faces_per_side` to keep them. Does nothing without `face_centers`
+    pub fn sort_faces(&mut self, camera: [f32; 3]) {
+        if self.face_centers.is_none() {
+            return;
+        }
+        self.sort_range(0..self.indices.len(), camera);
+        self.side_ranges = None;
+    }
+
+    /// `sort_faces` that only reorders faces inside each of the `side_ranges` so per side draws keep working. Faces of
+    /// different sides don't blend in order. Sorts all faces together when the mesh has no `side_ranges`
+    pub fn sort_faces_per_side(&mut self, camera: [f32; 3]) {
+        match self.side_ranges.clone() {
+            Some(ranges) => {
+                for range in ranges {
+                    self.sort_range(range, camera);
+                }
+            }
+            None => self.sort_faces(camera),
+        }
+    }
+
+    fn sort_range(&mut self, range: Range<usize>, camera: [f32; 3]) {
+        let Some(centers) = &self.face_centers else {
+            return;
+        };
+        let camera = glam::Vec3::from(camera);
+        let indices = &mut self.indices[range];
+        let mut faces = indices
+            .chunks_exact(6)
+            .map(|face| {
+                // Every index of a face points into the same 4 vertices
+                let center = glam::Vec3::from(centers[face[0] as usize / 4]);
+                (center.distance_squared(camera), face)
+            })
+            .collect::<Vec<_>>();
+        faces.sort_by(|(distance, _), (other, _)| other.total_cmp(distance));
+        let sorted = faces
+            .into_iter()
+            .flat_map(|(_, face)| face.iter().copied())
+            .collect::<Vec<_>>();
+        indices.copy_from_slice(&sorted);
+    }
 }
 
 pub struct MeshedChunk {
     pub chunk_mesh: VoxMesh,
+    /// Alpha tested faces of `VoxelVisibility::Cutout` voxels
+    pub cutout_mesh: VoxMesh,
     pub transparent_mesh: VoxMesh,
+    /// Surface of the voxels marked smooth. Empty if the chunk has none
+    pub smooth_mesh: SmoothMesh,
     pub pos: ChunkPos,
 }
 
+/// The range of indices of each side in side order. Faces are built one side after another so each side is already a
+/// single range
+pub(crate) fn side_ranges(quads: &QuadGroups) -> [Range<usize>; 6] {
+    let mut start = 0;
+    [0, 1, 2, 3, 4, 5].map(|side| {
+        let end = start + quads.groups[side].len() * 6;
+        let range = start..end;
+        start = end;
+        range
+    })
+}
+
 fn light_to_intern(color: u8) -> f32 {
     match color {
         0 => 0.25,
@@ -1659,8 +2510,34 @@ fn light_to_intern(color: u8) -> f32 {
     }
 }
 
-fn ao_convert(ao: Vec<u32>) -> Vec<[f32; 4]> {
-    let mut res = Vec::new();
+/// Tangents of a face from its positions and uvs. Faces without a texture get any tangent along the face
+fn face_tangents(positions: &[[f32; 3]], uvs: &[[f32; 2]], normal: [f32; 3]) -> [[f32; 4]; 4] {
+    let normal = glam::Vec3::from(normal);
+    let [origin, one, two] = [0, 1, 2].map(|vertex| glam::Vec3::from(positions[vertex]));
+    let [origin_uv, one_uv, two_uv] = [0, 1, 2].map(|vertex| glam::Vec2::from(uvs[vertex]));
+    let (edge_one, edge_two) = (one - origin, two - origin);
+    let (uv_one, uv_two) = (one_uv - origin_uv, two_uv - origin_uv);
+    let determinant = uv_one.x * uv_two.y - uv_two.x * uv_one.y;
+    let (tangent, bitangent) = if determinant.abs() > f32::EPSILON {
+        (
+            (edge_one * uv_two.y - edge_two * uv_one.y) / determinant,
+            (edge_two * uv_one.x - edge_one * uv_two.x) / determinant,
+        )
+    } else {
+        (edge_one, edge_two)
+    };
+    // Keep the tangent on the face
+    let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+    let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 {
+        -1.0
+    } else {
+        1.0
+    };
+    [tangent.extend(handedness).into(); 4]
+}
+
+fn ao_convert(ao: &[u32]) -> Vec<[f32; 4]> {
+    let mut res = Vec::with_capacity(ao.len());
     for value in ao {
         match value {
             0 => res.extend_from_slice(&[[0.1, 0.1, 0.1, 1.0]]),
@@ -1671,3 +2548,686 @@ fn ao_convert(ao: Vec<u32>) -> Vec<[f32; 4]> {
     }
     res
 }
+
+#[cfg(all(test, feature = "light"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    const CENTER: ChunkPos = ChunkPos(mint::Vector3 { x: 0, y: 0, z: 0 });
+
+    // A stone floor at y 0 with a slab in the open and a slab under a roof, lit by the sky
+    fn lit_world() -> ChunkMap<BlockData, BlockRegistry> {
+        let (registry, _, _) = registries();
+        let mut world = ChunkMap::default();
+        for pos in CENTER.neighbors().iter().chain([CENTER].iter()) {
+            world.insert(*pos, ChunkData::default());
+        }
+        for x in -16..32 {
+            for z in -16..32 {
+                world.set_voxel(VoxelPos::new(x, 0, z), block("stone"));
+            }
+        }
+        for x in 8..14 {
+            for z in 8..14 {
+                world.set_voxel(VoxelPos::new(x, 4, z), block("stone"));
+            }
+        }
+        world.set_voxel(VoxelPos::new(5, 1, 5), block("slab"));
+        world.set_voxel(VoxelPos::new(10, 1, 10), block("slab"));
+        let mut heightmaps = Heightmaps::default();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                propagate_sky_light(&mut world, &mut heightmaps, &registry, [x, z]);
+            }
+        }
+        world
+    }
+
+    fn mesh(world: &ChunkMap<BlockData, BlockRegistry>, options: &MeshOptions) -> MeshedChunk {
+        let (vox_registry, geo_registry, asset_registry) = registries();
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            world[&CENTER].clone(),
+            world.neighbors(CENTER).unwrap(),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        full_mesh_with_options(&asset_registry, &boundary, *CENTER, options)
+    }
+
+    // Values of every vertex of the top face of the voxel at x, z whose top is at y
+    fn top_face<T: Copy>(mesh: &VoxMesh, values: &[T], x: f32, y: f32, z: f32) -> Vec<T> {
+        mesh.vertices
+            .iter()
+            .zip(&mesh.normals)
+            .zip(values)
+            .filter(|((vertex, normal), _)| {
+                normal[1] > 0.5
+                    && vertex[1] == y
+                    && (x..=x + 1.0).contains(&vertex[0])
+                    && (z..=z + 1.0).contains(&vertex[2])
+            })
+            .map(|(_, value)| *value)
+            .collect()
+    }
+
+    #[test]
+    fn stored_light() {
+        let meshed = mesh(&lit_world(), &MeshOptions::default());
+        let mesh = &meshed.chunk_mesh;
+        let colors = mesh.colors.as_ref().unwrap();
+        let open = top_face(mesh, colors, 5.0, 1.5, 5.0);
+        assert_eq!(open.len(), 4);
+        assert!(open.iter().all(|color| color[0] > 0.9));
+        let covered = top_face(mesh, colors, 10.0, 1.5, 10.0);
+        assert_eq!(covered.len(), 4);
+        assert!(covered.iter().all(|color| color[0] < 0.9 && color[0] > 0.3));
+
+        // Chunks that were never lit are drawn full bright
+        let (vox_registry, geo_registry, asset_registry) = registries();
+        let mut chunk = ChunkData::default();
+        chunk.set(RelativeVoxelPos::new(1, 1, 1), block("stone"));
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            chunk,
+            std::array::from_fn(|_| ChunkData::default()),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        let meshed = full_mesh(&asset_registry, &boundary, *CENTER);
+        assert!(meshed
+            .chunk_mesh
+            .colors
+            .unwrap()
+            .iter()
+            .all(|color| color[0] == 1.0));
+    }
+
+    #[test]
+    fn separate_light() {
+        let world = lit_world();
+        let meshed = mesh(
+            &world,
+            &MeshOptions {
+                light_mode: LightMode::Separate,
+                ..Default::default()
+            },
+        );
+        let mesh = &meshed.chunk_mesh;
+        assert!(mesh.colors.is_none());
+        let sky = mesh.sky_light.as_ref().unwrap();
+        let block = mesh.block_light.as_ref().unwrap();
+        let ao = mesh.ao.as_ref().unwrap();
+        assert_eq!(sky.len(), mesh.vertices.len());
+        assert_eq!(block.len(), mesh.vertices.len());
+        assert_eq!(ao.len(), mesh.vertices.len());
+        assert!(block.iter().all(|level| *level == 0.0));
+        assert!(top_face(mesh, sky, 5.0, 1.5, 5.0)
+            .iter()
+            .all(|level| *level > 0.95));
+        assert!(top_face(mesh, sky, 10.0, 1.5, 10.0)
+            .iter()
+            .all(|level| *level < 0.9 && *level > 0.0));
+
+        // Baked colors are the default and cover the transparent mesh too
+        let baked = self::mesh(&world, &MeshOptions::default());
+        assert!(baked.chunk_mesh.sky_light.is_none());
+        assert_eq!(
+            baked.transparent_mesh.colors.unwrap().len(),
+            baked.transparent_mesh.vertices.len()
+        );
+    }
+
+    #[test]
+    fn emissive() {
+        let (mut vox_registry, geo_registry, asset_registry) = registries();
+        vox_registry.get_mut("vinox:slab").unwrap().emission = Some(15);
+        let mut chunk = ChunkData::default();
+        chunk.set(RelativeVoxelPos::new(1, 1, 1), block("stone"));
+        chunk.set(RelativeVoxelPos::new(5, 1, 1), block("slab"));
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            chunk,
+            std::array::from_fn(|_| ChunkData::default()),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        let mesh = full_mesh(&asset_registry, &boundary, *CENTER).chunk_mesh;
+        let emissive = mesh.emissive.as_ref().unwrap();
+        assert_eq!(emissive.len(), mesh.vertices.len());
+        for (vertex, emissive) in mesh.vertices.iter().zip(emissive) {
+            let expected = if vertex[0] >= 5.0 { 1.0 } else { 0.0 };
+            assert_eq!(*emissive, expected);
+        }
+    }
+
+    #[test]
+    fn culls_between_cubes() {
+        let (vox_registry, geo_registry, asset_registry) = registries();
+        let mut world = ChunkMap::<BlockData, BlockRegistry>::default();
+        for pos in CENTER.neighbors().iter().chain([CENTER].iter()) {
+            world.insert(*pos, ChunkData::default());
+        }
+        let mut seed = 12345u64;
+        let mut solid = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            seed >> 62 != 0
+        };
+        for x in -1..17 {
+            for y in -1..17 {
+                for z in -1..17 {
+                    if solid() {
+                        world.set_voxel(VoxelPos::new(x, y, z), block("stone"));
+                    }
+                }
+            }
+        }
+        let is_stone =
+            |x, y, z| world.get_voxel(VoxelPos::new(x, y, z)).unwrap().identifier == "vinox:stone";
+        let mut faces = 0;
+        for x in 0..16 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    if is_stone(x, y, z) {
+                        faces += [
+                            (-1, 0, 0),
+                            (1, 0, 0),
+                            (0, -1, 0),
+                            (0, 1, 0),
+                            (0, 0, -1),
+                            (0, 0, 1),
+                        ]
+                        .iter()
+                        .filter(|(dx, dy, dz)| !is_stone(x + dx, y + dy, z + dz))
+                        .count();
+                    }
+                }
+            }
+        }
+
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            world[&CENTER].clone(),
+            world.neighbors(CENTER).unwrap(),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        let mesh = full_mesh(&asset_registry, &boundary, *CENTER).chunk_mesh;
+        assert_eq!(mesh.vertices.len(), faces * 4);
+    }
+
+    #[test]
+    fn sorts_transparent_faces() {
+        let mut world = ChunkMap::<BlockData, BlockRegistry>::default();
+        for pos in CENTER.neighbors().iter().chain([CENTER].iter()) {
+            world.insert(*pos, ChunkData::default());
+        }
+        for x in (0..16).step_by(2) {
+            world.set_voxel(VoxelPos::new(x, 3, 3), block("glass"));
+        }
+        let camera = [-10.0, 3.5, 3.5];
+        // Distance to the camera of every face of indices
+        let distances = |mesh: &VoxMesh, indices: &[u32]| {
+            let centers = mesh.face_centers.as_ref().unwrap();
+            indices
+                .chunks(6)
+                .map(|face| {
+                    glam::Vec3::from(centers[face[0] as usize / 4])
+                        .distance(glam::Vec3::from(camera))
+                })
+                .collect::<Vec<_>>()
+        };
+        let back_to_front =
+            |distances: Vec<f32>| distances.windows(2).all(|pair| pair[0] >= pair[1]);
+        let options = MeshOptions {
+            side_buckets: true,
+            ..Default::default()
+        };
+
+        let mut mesh = self::mesh(&world, &options).transparent_mesh;
+        let mut before = mesh.indices.clone();
+        mesh.sort_faces(camera);
+        assert!(back_to_front(distances(&mesh, &mesh.indices)));
+        assert!(mesh.side_ranges.is_none());
+        let mut after = mesh.indices.clone();
+        before.sort();
+        after.sort();
+        assert_eq!(before, after);
+
+        let mut mesh = self::mesh(&world, &options).transparent_mesh;
+        mesh.sort_faces_per_side(camera);
+        let ranges = mesh.side_ranges.clone().unwrap();
+        assert!(!back_to_front(distances(&mesh, &mesh.indices)));
+        for range in ranges {
+            assert!(back_to_front(distances(&mesh, &mesh.indices[range])));
+        }
+    }
+
+    #[test]
+    fn cutout_pass() {
+        let (mut vox_registry, geo_registry, asset_registry) = registries();
+        vox_registry.insert(
+            "vinox:leaves".into(),
+            Block {
+                identifier: "vinox:leaves".into(),
+                geometry: Some(BlockGeometry::Block),
+                visibility: Some(VoxelVisibility::Cutout),
+                ..Default::default()
+            },
+        );
+        let mut chunk = ChunkData::default();
+        chunk.set(RelativeVoxelPos::new(2, 3, 3), block("glass"));
+        chunk.set(RelativeVoxelPos::new(3, 3, 3), block("leaves"));
+        chunk.set(RelativeVoxelPos::new(4, 3, 3), block("leaves"));
+        chunk.set(RelativeVoxelPos::new(5, 3, 3), block("stone"));
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            chunk,
+            std::array::from_fn(|_| ChunkData::default()),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        let meshed = full_mesh(&asset_registry, &boundary, *CENTER);
+        // The leaves cull between each other and against stone but not against glass
+        assert_eq!(meshed.cutout_mesh.vertices.len(), 9 * 4);
+        // Leaves hide neither the stone nor the glass
+        assert_eq!(meshed.chunk_mesh.vertices.len(), 6 * 4);
+        assert_eq!(meshed.transparent_mesh.vertices.len(), 6 * 4);
+    }
+
+    #[test]
+    fn rotated_normals_and_tangents() {
+        let (mut vox_registry, mut geo_registry, mut asset_registry) = registries();
+        let mut rotated = Geometry {
+            name: "rotated".into(),
+            blocks: [false; 6],
+            ..Default::default()
+        };
+        rotated.element.rotation = (0, 45, 0);
+        geo_registry.insert("vinox:rotated".to_string(), rotated);
+        vox_registry.insert(
+            "vinox:rotated".into(),
+            Block {
+                identifier: "vinox:rotated".into(),
+                geometry: Some(BlockGeometry::Custom("vinox:rotated".into())),
+                visibility: Some(VoxelVisibility::Opaque),
+                ..Default::default()
+            },
+        );
+        let uvs = asset_registry.texture_uvs["vinox:stone"];
+        asset_registry
+            .texture_uvs
+            .insert("vinox:rotated".into(), uvs);
+        let mut chunk = ChunkData::default();
+        chunk.set(RelativeVoxelPos::new(3, 3, 3), block("rotated"));
+        chunk.set(RelativeVoxelPos::new(8, 3, 3), block("stone"));
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            chunk,
+            std::array::from_fn(|_| ChunkData::default()),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        let mesh = full_mesh_with_options(
+            &asset_registry,
+            &boundary,
+            *CENTER,
+            &MeshOptions {
+                tangents: true,
+                ..Default::default()
+            },
+        )
+        .chunk_mesh;
+        let tangents = mesh.tangents.as_ref().unwrap();
+        let uvs = mesh.uvs.as_ref().unwrap();
+        assert_eq!(tangents.len(), mesh.vertices.len());
+        let mut diagonal = 0;
+        for face in 0..mesh.vertices.len() / 4 {
+            let vertex = |i: usize| glam::Vec3::from(mesh.vertices[face * 4 + i]);
+            let triangle = mesh.indices[face * 6..face * 6 + 3]
+                .iter()
+                .map(|index| glam::Vec3::from(mesh.vertices[*index as usize]))
+                .collect::<Vec<_>>();
+            let facing = (triangle[1] - triangle[0])
+                .cross(triangle[2] - triangle[0])
+                .normalize();
+            let normal = glam::Vec3::from(mesh.normals[face * 4]);
+            if normal.x.abs() > 0.1 && normal.z.abs() > 0.1 {
+                diagonal += 1;
+            }
+            // Normals follow the rotated faces
+            assert!(facing.dot(normal).abs() > 0.99, "face {face}");
+            // Tangents lie in the face and point to where u grows
+            let tangent = glam::Vec4::from(tangents[face * 4]).truncate();
+            assert!(tangent.dot(normal).abs() < 1e-4);
+            let du = uvs[face * 4 + 1][0] - uvs[face * 4][0];
+            if du.abs() > 1e-6 {
+                assert!((vertex(1) - vertex(0)).dot(tangent) * du > 0.0);
+            }
+        }
+        // The 4 sides of the rotated cube
+        assert_eq!(diagonal, 4);
+    }
+
+    #[test]
+    fn texture_variance() {
+        let mut seen = [0; 8];
+        for x in 0..64 {
+            for z in 0..64 {
+                let pos = mint::Vector3 { x, y: 3, z };
+                let variant = texture_variant(pos, 3, 7);
+                assert_eq!(variant, texture_variant(pos, 3, 7));
+                seen[variant as usize] += 1;
+            }
+        }
+        // 512 each if the variants were spread evenly
+        assert!(seen.iter().all(|count| *count > 400));
+
+        let (mut vox_registry, mut geo_registry, asset_registry) = registries();
+        let mut varied = Geometry {
+            name: "varied".into(),
+            ..Default::default()
+        };
+        varied.element.cubes[0].texture_variance = [true; 6];
+        geo_registry.insert("vinox:varied".to_string(), varied);
+        vox_registry.get_mut("vinox:stone").unwrap().geometry =
+            Some(BlockGeometry::Custom("vinox:varied".into()));
+        let mut chunk = ChunkData::default();
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set(RelativeVoxelPos::new(x, 0, z), block("stone"));
+            }
+        }
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            chunk,
+            std::array::from_fn(|_| ChunkData::default()),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        let mesh = |options: MeshOptions| {
+            full_mesh_with_options(&asset_registry, &boundary, *CENTER, &options).chunk_mesh
+        };
+        let plain = mesh(MeshOptions::default());
+        assert_eq!(plain.uvs, mesh(MeshOptions::default()).uvs);
+        let reseeded = mesh(MeshOptions {
+            variance_seed: 1,
+            ..Default::default()
+        });
+        assert_ne!(plain.uvs, reseeded.uvs);
+        let uvs = plain.uvs.unwrap();
+        let distinct = uvs
+            .chunks(4)
+            .map(|face| format!("{face:?}"))
+            .collect::<std::collections::HashSet<_>>();
+        assert!(distinct.len() >= 8);
+        // Faces with different variants aren't merged
+        let greedy = mesh(MeshOptions {
+            greedy: true,
+            ..Default::default()
+        });
+        assert_eq!(greedy.vertices.len(), plain.vertices.len());
+    }
+
+    // Meshes connected shelves at positions with a texture sheet of size pixels
+    fn shelf_mesh(
+        mode: ConnectedTextures,
+        positions: &[(u32, u32, u32)],
+        size: (f32, f32),
+    ) -> VoxMesh {
+        let (mut vox_registry, geo_registry, mut asset_registry) = registries();
+        vox_registry.insert(
+            "vinox:shelf".into(),
+            Block {
+                identifier: "vinox:shelf".into(),
+                geometry: Some(BlockGeometry::Block),
+                visibility: Some(VoxelVisibility::Opaque),
+                connected: Some(mode),
+                ..Default::default()
+            },
+        );
+        asset_registry.texture_uvs.insert(
+            "vinox:shelf".into(),
+            [UVRect {
+                x: 0.,
+                y: 0.,
+                w: size.0,
+                h: size.1,
+            }; 6],
+        );
+        asset_registry.texture_size = mint::Point2 {
+            x: size.0,
+            y: size.1,
+        };
+        let mut chunk = ChunkData::default();
+        for (x, y, z) in positions {
+            chunk.set(RelativeVoxelPos::new(*x, *y, *z), block("shelf"));
+        }
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            chunk,
+            std::array::from_fn(|_| ChunkData::default()),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        full_mesh_with_options(
+            &asset_registry,
+            &boundary,
+            *CENTER,
+            &MeshOptions {
+                greedy: true,
+                ..Default::default()
+            },
+        )
+        .chunk_mesh
+    }
+
+    // Column and row of the 16 pixel tile of the face with normal centered at center
+    fn face_tile(
+        mesh: &VoxMesh,
+        size: (f32, f32),
+        normal: [f32; 3],
+        center: [f32; 3],
+    ) -> (u32, u32) {
+        let rects = mesh.uv_rects.as_ref().unwrap();
+        (0..mesh.vertices.len())
+            .step_by(4)
+            .find(|face| {
+                let face_center = [0, 1, 2]
+                    .map(|axis| (0..4).map(|i| mesh.vertices[face + i][axis]).sum::<f32>() / 4.0);
+                mesh.normals[*face] == normal && face_center == center
+            })
+            .map(|face| {
+                let rect = rects[face];
+                (
+                    (rect[0].min(rect[2]) * size.0 / 16.0).round() as u32,
+                    (rect[1].min(rect[3]) * size.1 / 16.0).round() as u32,
+                )
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn connected_tiles() {
+        let mut tiles = (0..=255)
+            .map(|mask| connected_tile(ConnectedTextures::Full, mask))
+            .collect::<Vec<_>>();
+        tiles.sort();
+        tiles.dedup();
+        assert_eq!(tiles.len(), 47);
+        assert_eq!(connected_tile(ConnectedTextures::Full, 0), (0, 0));
+        assert_eq!(connected_tile(ConnectedTextures::Full, 255), (6, 5));
+        assert_eq!(
+            connected_tile(ConnectedTextures::Edges, 0b0101_0101),
+            (3, 3)
+        );
+        assert_eq!(
+            connected_tile(ConnectedTextures::Edges, 0b0000_0100),
+            (2, 0)
+        );
+
+        // A 3x3 wall at z 4 with one more shelf in front of its center on the south side
+        let mut wall = (4..7)
+            .flat_map(|x| (4..7).map(move |y| (x, y, 4)))
+            .collect::<Vec<_>>();
+        wall.push((5, 5, 3));
+        for (mode, size) in [
+            (ConnectedTextures::Full, (128., 96.)),
+            (ConnectedTextures::Edges, (64., 64.)),
+        ] {
+            let mesh = shelf_mesh(mode, &wall, size);
+            let tile = |normal, center| face_tile(&mesh, size, normal, center);
+            assert_eq!(
+                tile([0., 0., 1.], [5.5, 5.5, 5.0]),
+                connected_tile(mode, 255)
+            );
+            // Right (+x) and left connect but up is covered by the shelf in front
+            assert_eq!(
+                tile([0., 0., -1.], [5.5, 4.5, 4.0]),
+                connected_tile(mode, 0b0100_0100)
+            );
+            // Right is -x on the north side so the corner at x 4 connects up and left. The up left corner is
+            // dropped as it is the same tile
+            assert_eq!(
+                tile([0., 0., 1.], [4.5, 4.5, 5.0]),
+                connected_tile(mode, 0b1100_0001)
+            );
+            assert_eq!(
+                connected_tile(mode, 0b1100_0001),
+                connected_tile(mode, 0b1100_0011)
+            );
+        }
+    }
+
+    #[test]
+    fn connected_tops() {
+        let floor = (4..7)
+            .flat_map(|x| (4..7).map(move |z| (x, 4, z)))
+            .collect::<Vec<_>>();
+        let size = (64., 64.);
+        let mesh = shelf_mesh(ConnectedTextures::Edges, &floor, size);
+        let uvs = mesh.uvs.as_ref().unwrap();
+        for (normal, y) in [([0., 1., 0.], 5.0), ([0., -1., 0.], 4.0)] {
+            // The corner at x 4, z 4 connects towards +x and +z. Work out which of its tiles sides those are
+            let face = (0..mesh.vertices.len())
+                .step_by(4)
+                .find(|face| {
+                    let center = [0, 2].map(|axis| {
+                        (0..4).map(|i| mesh.vertices[face + i][axis]).sum::<f32>() / 4.0
+                    });
+                    mesh.normals[*face] == normal && center == [4.5, 4.5]
+                })
+                .unwrap();
+            let mut mask = 0u8;
+            for from in face..face + 4 {
+                for to in face..face + 4 {
+                    let step =
+                        [0, 1, 2].map(|axis| mesh.vertices[to][axis] - mesh.vertices[from][axis]);
+                    if step != [1., 0., 0.] && step != [0., 0., 1.] {
+                        continue;
+                    }
+                    let (du, dv) = (uvs[to][0] - uvs[from][0], uvs[to][1] - uvs[from][1]);
+                    // Bits of up, right, down and left
+                    mask |= 1
+                        << match () {
+                            _ if du > 0.0 => 2,
+                            _ if du < 0.0 => 6,
+                            _ if dv < 0.0 => 0,
+                            _ => 4,
+                        };
+                }
+            }
+            assert_eq!(
+                face_tile(&mesh, size, normal, [4.5, y, 4.5]),
+                connected_tile(ConnectedTextures::Edges, mask)
+            );
+        }
+    }
+
+    #[test]
+    fn texture_layers() {
+        let (vox_registry, geo_registry, mut asset_registry) = registries();
+        asset_registry
+            .texture_layers
+            .insert("vinox:stone".into(), [0, 1, 2, 3, 4, 5]);
+        asset_registry
+            .texture_layers
+            .insert("vinox:slab".into(), [7; 6]);
+        let mut chunk = ChunkData::default();
+        for x in 0..4 {
+            for z in 0..4 {
+                chunk.set(RelativeVoxelPos::new(x, 0, z), block("stone"));
+            }
+        }
+        chunk.set(RelativeVoxelPos::new(8, 8, 8), block("slab"));
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            chunk,
+            std::array::from_fn(|_| ChunkData::default()),
+            CENTER,
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        let mesh = |texture_mode| {
+            full_mesh_with_options(
+                &asset_registry,
+                &boundary,
+                *CENTER,
+                &MeshOptions {
+                    greedy: true,
+                    texture_mode,
+                    ..Default::default()
+                },
+            )
+            .chunk_mesh
+        };
+        let layered = mesh(TextureMode::Layers);
+        assert!(layered.uv_rects.is_none());
+        let layers = layered.texture_layers.as_ref().unwrap();
+        let uvs = layered.uvs.as_ref().unwrap();
+        assert_eq!(layers.len(), layered.vertices.len());
+        for face in (0..layered.vertices.len()).step_by(4) {
+            let normal = layered.normals[face];
+            let expected = if layered.vertices[face][0] >= 8.0 {
+                7
+            } else {
+                // Textures are in up, down, west, east, north, south order
+                [
+                    [0., 1., 0.],
+                    [0., -1., 0.],
+                    [-1., 0., 0.],
+                    [1., 0., 0.],
+                    [0., 0., 1.],
+                    [0., 0., -1.],
+                ]
+                .iter()
+                .position(|side| *side == normal)
+                .unwrap() as u32
+            };
+            assert!(layers[face..face + 4]
+                .iter()
+                .all(|layer| *layer == expected));
+        }
+        // The top of the floor is one quad whose uvs repeat the layer once per voxel
+        let top = (0..layered.vertices.len())
+            .step_by(4)
+            .find(|face| layers[*face] == 0)
+            .unwrap();
+        assert!(uvs[top..top + 4].iter().any(|uv| uv.contains(&4.0)));
+
+        let atlas = mesh(TextureMode::Atlas);
+        assert!(atlas.texture_layers.is_none());
+        assert_eq!(atlas.vertices, layered.vertices);
+    }
+}
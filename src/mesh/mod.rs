@@ -1,2 +1,8 @@
+pub mod batch;
 pub mod chunk;
+pub mod greedy;
+pub mod lod;
 pub mod mesher;
+pub mod outline;
+pub mod packed;
+pub mod smooth;
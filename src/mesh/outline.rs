@@ -0,0 +1,330 @@
+use ahash::{HashSet, HashSetExt};
+use serde::Serialize;
+
+use crate::prelude::*;
+
+/// How far the outline is pushed out from the geometry so it doesn't z-fight with the block
+const INFLATE: f32 = 1.0 / 512.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutlineStyle {
+    /// A line list where every two indices form a line
+    Lines,
+    /// Every edge becomes two crossed double sided quads of the given width so it can be drawn as a triangle list
+    Quads(f32),
+}
+
+/// An edge of a cube along `axis` from start to end. Points are in block space (0.0..1.0)
+#[derive(Clone, Copy)]
+struct Edge {
+    axis: usize,
+    start: glam::Vec3,
+    end: glam::Vec3,
+    cube: usize,
+}
+
+/// Builds an outline mesh tracing the union of a voxels `BlockGeo` cuboids with the block and cube rotations applied.
+/// Edges that lie flat inside the union (ie where two cubes of a stair meet) are left out. The mesh is in block space
+/// (0.0..1.0) so translate it to the voxels position when drawing it. pos picks the variant the voxel is drawn with
+pub fn selection_outline<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    voxel: &V,
+    pos: VoxelPos,
+    vox_registry: &R,
+    geo_registry: &GeometryRegistry,
+    style: OutlineStyle,
+) -> VoxMesh {
+    let mut geo_pal = GeoPalette::default();
+    let variant = pick_variant(*pos, &voxel.variant_weights(Some(vox_registry)));
+    let geo = voxel
+        .to_geo_idx_variant(
+            Some(&mut geo_pal),
+            Some(geo_registry),
+            Some(vox_registry),
+            variant,
+        )
+        .and_then(|geo_index| geo_pal.palette.get(geo_index).cloned())
+        .unwrap_or_default();
+    geo_outline(&geo, style)
+}
+
+/// Builds an outline mesh for the given geometry. See `selection_outline`
+pub fn geo_outline(geo: &BlockGeo, style: OutlineStyle) -> VoxMesh {
+    let straight_cubes = geo
+        .cubes
+        .iter()
+        .enumerate()
+        .filter(|(cube_num, cube)| !geo.is_rotated(*cube_num) && !is_flat(cube))
+        .map(|(_, cube)| (cube.min(), cube.max()))
+        .collect::<Vec<_>>();
+
+    let mut edges = Vec::new();
+    let mut seen = HashSet::new();
+    for (cube_num, cube) in geo.cubes.iter().enumerate() {
+        let (min, max) = (cube.min(), cube.max());
+        for edge in cube_edges(min, max, cube_num) {
+            if geo.is_rotated(cube_num) {
+                edges.push(edge);
+                continue;
+            }
+            // Split the edge wherever another cube starts or ends so each piece is either fully on the outline or not
+            let mut cuts = vec![edge.start[edge.axis], edge.end[edge.axis]];
+            for (other_min, other_max) in &straight_cubes {
+                for cut in [other_min[edge.axis], other_max[edge.axis]] {
+                    if cut > edge.start[edge.axis] && cut < edge.end[edge.axis] {
+                        cuts.push(cut);
+                    }
+                }
+            }
+            cuts.sort_by(|a, b| a.total_cmp(b));
+            cuts.dedup();
+            for cut in cuts.windows(2) {
+                let (mut start, mut end) = (edge.start, edge.end);
+                start[edge.axis] = cut[0];
+                end[edge.axis] = cut[1];
+                let key = (
+                    edge.axis,
+                    (start * 16.0).round().as_ivec3().to_array(),
+                    (end * 16.0).round().as_ivec3().to_array(),
+                );
+                if seen.contains(&key)
+                    || (!is_flat(cube)
+                        && !is_crease(&straight_cubes, edge.axis, (start + end) / 2.0))
+                {
+                    continue;
+                }
+                seen.insert(key);
+                edges.push(Edge { start, end, ..edge });
+            }
+        }
+    }
+
+    let edges = merge_edges(geo, edges);
+
+    let center = geo_center(geo);
+    let mut mesh = VoxMesh::default();
+    for edge in edges {
+        let transform = |point: glam::Vec3| -> [f32; 3] {
+            let point = if geo.is_rotated(edge.cube) {
+                geo.rotate_point(edge.cube, point)
+            } else {
+                point
+            };
+            (point + (point - center).signum() * INFLATE).into()
+        };
+        match style {
+            OutlineStyle::Lines => {
+                let start = mesh.vertices.len() as u32;
+                mesh.vertices.push(transform(edge.start));
+                mesh.vertices.push(transform(edge.end));
+                let direction = geo.rotate_direction(edge.cube, edge.end - edge.start);
+                let normal: [f32; 3] = direction.normalize_or_zero().into();
+                mesh.normals.extend_from_slice(&[normal, normal]);
+                mesh.indices.extend_from_slice(&[start, start + 1]);
+            }
+            OutlineStyle::Quads(width) => {
+                for offset_axis in [(edge.axis + 1) % 3, (edge.axis + 2) % 3] {
+                    let mut offset = glam::Vec3::ZERO;
+                    offset[offset_axis] = width / 2.0;
+                    let start = mesh.vertices.len() as u32;
+                    mesh.vertices.extend_from_slice(&[
+                        transform(edge.start - offset),
+                        transform(edge.start + offset),
+                        transform(edge.end - offset),
+                        transform(edge.end + offset),
+                    ]);
+                    let mut normal = glam::Vec3::ZERO;
+                    normal[3 - edge.axis - offset_axis] = 1.0;
+                    let normal: [f32; 3] = geo.rotate_direction(edge.cube, normal).into();
+                    mesh.normals
+                        .extend_from_slice(&[normal, normal, normal, normal]);
+                    // Both windings so the quad is visible from either side
+                    mesh.indices.extend_from_slice(&[
+                        start,
+                        start + 1,
+                        start + 2,
+                        start + 1,
+                        start + 3,
+                        start + 2,
+                        start,
+                        start + 2,
+                        start + 1,
+                        start + 1,
+                        start + 2,
+                        start + 3,
+                    ]);
+                }
+            }
+        }
+    }
+    mesh
+}
+
+/// Joins unrotated edges that continue each other in a straight line back into one edge
+fn merge_edges(geo: &BlockGeo, edges: Vec<Edge>) -> Vec<Edge> {
+    let (mut straight, mut merged): (Vec<Edge>, Vec<Edge>) = edges
+        .into_iter()
+        .partition(|edge| !geo.is_rotated(edge.cube));
+    let sort_key = |edge: &Edge| {
+        let (one, two) = ((edge.axis + 1) % 3, (edge.axis + 2) % 3);
+        [
+            edge.axis as f32,
+            edge.start[one],
+            edge.start[two],
+            edge.start[edge.axis],
+        ]
+    };
+    straight.sort_by(|a, b| {
+        sort_key(a)
+            .partial_cmp(&sort_key(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let mut current: Option<Edge> = None;
+    for edge in straight {
+        match current.as_mut() {
+            Some(last) if last.axis == edge.axis && last.end == edge.start => last.end = edge.end,
+            _ => merged.extend(current.replace(edge)),
+        }
+    }
+    merged.extend(current);
+    merged
+}
+
+/// Cubes with no volume such as a flat plane. Every edge of them is drawn
+fn is_flat(cube: &FaceDescript) -> bool {
+    cube.origin.0 == cube.end.0 || cube.origin.1 == cube.end.1 || cube.origin.2 == cube.end.2
+}
+
+/// The 12 edges of a cube
+fn cube_edges(min: glam::Vec3, max: glam::Vec3, cube: usize) -> Vec<Edge> {
+    let mut edges = Vec::with_capacity(12);
+    for axis in 0..3 {
+        let (one, two) = ((axis + 1) % 3, (axis + 2) % 3);
+        for (first, second) in [(min, min), (min, max), (max, min), (max, max)] {
+            let mut start = min;
+            start[one] = first[one];
+            start[two] = second[two];
+            let mut end = start;
+            end[axis] = max[axis];
+            edges.push(Edge {
+                axis,
+                start,
+                end,
+                cube,
+            });
+        }
+    }
+    edges
+}
+
+/// Checks the four quadrants around an edge. It is part of the outline unless the union is flat there
+fn is_crease(cubes: &[(glam::Vec3, glam::Vec3)], axis: usize, point: glam::Vec3) -> bool {
+    const EPSILON: f32 = 1.0 / 64.0;
+    let (one, two) = ((axis + 1) % 3, (axis + 2) % 3);
+    let inside = [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].map(|(a, b)| {
+        let mut sample = point;
+        sample[one] += a * EPSILON;
+        sample[two] += b * EPSILON;
+        cubes
+            .iter()
+            .any(|(min, max)| sample.cmpgt(*min).all() && sample.cmplt(*max).all())
+    });
+    match inside.iter().filter(|inside| **inside).count() {
+        1 | 3 => true,
+        // Two diagonal quadrants meet at a single edge
+        2 => inside[0] == inside[2],
+        _ => false,
+    }
+}
+
+fn geo_center(geo: &BlockGeo) -> glam::Vec3 {
+    if geo.cubes.is_empty() {
+        return glam::Vec3::splat(0.5);
+    }
+    let mut min = glam::Vec3::splat(f32::INFINITY);
+    let mut max = glam::Vec3::splat(f32::NEG_INFINITY);
+    for cube in &geo.cubes {
+        min = min.min(cube.min());
+        max = max.max(cube.max());
+    }
+    (min + max) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Corners = ((i8, i8, i8), (i8, i8, i8));
+
+    fn cubes(boxes: &[Corners]) -> BlockGeo {
+        let mut geo = BlockGeo::default();
+        let cube = geo.cubes[0];
+        geo.cubes = boxes
+            .iter()
+            .map(|&(origin, end)| FaceDescript {
+                origin,
+                end,
+                ..cube
+            })
+            .collect();
+        geo
+    }
+
+    fn edges(geo: &BlockGeo) -> usize {
+        geo_outline(geo, OutlineStyle::Lines).indices.len() / 2
+    }
+
+    #[test]
+    fn cube_outline() {
+        assert_eq!(edges(&BlockGeo::default()), 12);
+        // Two halves of a cube trace the same outline as one cube
+        assert_eq!(
+            edges(&cubes(&[
+                ((0, 0, 0), (8, 16, 16)),
+                ((8, 0, 0), (16, 16, 16))
+            ])),
+            12
+        );
+    }
+
+    #[test]
+    fn stair_outline() {
+        // Two L shaped profiles of 6 edges joined by 6 edges
+        assert_eq!(
+            edges(&cubes(&[
+                ((0, 0, 0), (16, 8, 16)),
+                ((8, 8, 0), (16, 16, 16))
+            ])),
+            18
+        );
+    }
+
+    #[test]
+    fn quad_outline() {
+        let mesh = geo_outline(&BlockGeo::default(), OutlineStyle::Quads(0.02));
+        // Two crossed quads per edge
+        assert_eq!(mesh.vertices.len(), 12 * 8);
+    }
+
+    #[cfg(feature = "block")]
+    #[test]
+    fn slab_selection() {
+        let (vox_registry, geo_registry, _) = crate::test_util::registries();
+        let mesh = selection_outline(
+            &crate::test_util::block("slab"),
+            VoxelPos::new(0, 0, 0),
+            &vox_registry,
+            &geo_registry,
+            OutlineStyle::Lines,
+        );
+        assert_eq!(mesh.indices.len() / 2, 12);
+        let top = mesh
+            .vertices
+            .iter()
+            .map(|vertex| vertex[1])
+            .fold(f32::MIN, f32::max);
+        assert!((top - 0.5).abs() < 0.01);
+    }
+}
@@ -0,0 +1,354 @@
+use serde::Serialize;
+
+use crate::prelude::*;
+
+/// A vertex of `VoxMesh::packed` in two u32s. The first holds x, y and z in 1/16 of a voxel from the chunks origin
+/// (9 bits each starting at the lowest bit), the side in side order (3 bits) and AO (2 bits, 0 is fully occluded).
+/// The second holds sky and block light (4 bits each), the texture array layer (16 bits), the variant (3 bits) and
+/// the light the voxel gives off (4 bits).
+///
+/// Packed vertices have no uvs. Shaders take u and v in voxels from the position along the right and down (the
+/// opposite of up) `TEXTURE_AXES` of the side, swap them if bit 2 of the variant is set, negate u for bit 0 and v for
+/// bit 1 and sample with repeat so textures repeat every voxel. The variant is the `texture_variant` of the face
+/// with the flips of the geometrys uvs (`Face::packed_flips`) applied to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PackedVertex {
+    pub position: [u32; 3],
+    pub side: u8,
+    pub ao: u8,
+    pub sky_light: u8,
+    pub block_light: u8,
+    pub layer: u16,
+    pub variant: u8,
+    pub emission: u8,
+}
+
+impl PackedVertex {
+    /// Largest position on each axis. A chunk only needs up to `CHUNK_SIZE * 16`
+    pub const MAX_POSITION: u32 = (1 << 9) - 1;
+
+    pub fn pack(&self) -> [u32; 2] {
+        let [x, y, z] = self.position.map(|axis| axis.min(Self::MAX_POSITION));
+        [
+            x | y << 9 | z << 18 | (self.side as u32 & 0b111) << 27 | (self.ao as u32 & 0b11) << 30,
+            (self.sky_light as u32 & 0xF)
+                | (self.block_light as u32 & 0xF) << 4
+                | (self.layer as u32) << 8
+                | (self.variant as u32 & 0b111) << 24
+                | (self.emission as u32 & 0xF) << 27,
+        ]
+    }
+
+    pub fn unpack([first, second]: [u32; 2]) -> Self {
+        Self {
+            position: [0, 9, 18].map(|shift| first >> shift & Self::MAX_POSITION),
+            side: (first >> 27 & 0b111) as u8,
+            ao: (first >> 30) as u8,
+            sky_light: (second & 0xF) as u8,
+            block_light: (second >> 4 & 0xF) as u8,
+            layer: (second >> 8) as u16,
+            variant: (second >> 24 & 0b111) as u8,
+            emission: (second >> 27 & 0xF) as u8,
+        }
+    }
+}
+
+/// Builds a mesh of only `VoxMesh::packed` vertices, indices and side ranges from the faces in quads. Light is always
+/// stored raw like `LightMode::Separate` and colored light is left out. Layers come from `AssetRegistry::texture_layers`
+/// so this needs `TextureMode::Layers`. None without it or when a face can't be packed (see `MeshOptions::packed`)
+pub(crate) fn packed_mesh<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    raw_chunk: &ChunkBoundary<V, R>,
+    chunk_pos: mint::Vector3<i32>,
+    quads: &QuadGroups,
+    options: &MeshOptions,
+) -> Option<VoxMesh> {
+    if options.texture_mode != TextureMode::Layers {
+        return None;
+    }
+    let vertex_count = quads.groups.iter().map(|quads| quads.len()).sum::<usize>() * 4;
+    let mut packed = Vec::with_capacity(vertex_count);
+    let mut indices = Vec::with_capacity(vertex_count / 4 * 6);
+    for face in quads.iter_with_ao(raw_chunk) {
+        indices.extend_from_slice(&face.indices(packed.len() as u32));
+        let world_pos = face.world_pos(chunk_pos, lod_scale(options.lod));
+        let variant = face.texture_variant(world_pos.into(), options.variance_seed, raw_chunk)
+            ^ face.packed_flips(raw_chunk)?;
+        let layer = u16::try_from(
+            face.texture_layer(face.side.texture_index(), raw_chunk)
+                .unwrap_or_default(),
+        )
+        .ok()?;
+        let [sky_light, block_light] = [LightChannel::Sky, LightChannel::Block].map(|channel| {
+            face_light_levels(&face, raw_chunk, channel)
+                .map(|level| (level * MAX_LIGHT as f32).round() as u8)
+        });
+        let aos = face.aos();
+        for (vertex, position) in face.packed_positions(raw_chunk)?.into_iter().enumerate() {
+            packed.push(
+                PackedVertex {
+                    position,
+                    side: face.side.index() as u8,
+                    ao: aos[vertex] as u8,
+                    sky_light: sky_light[vertex],
+                    block_light: block_light[vertex],
+                    layer,
+                    variant,
+                    emission: face.quad.data.emission,
+                }
+                .pack(),
+            );
+        }
+    }
+    Some(VoxMesh {
+        indices,
+        packed: Some(packed),
+        side_ranges: options.side_buckets.then(|| side_ranges(quads)),
+        ..Default::default()
+    })
+}
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    #[test]
+    fn round_trip() {
+        for vertex in [
+            PackedVertex {
+                position: [256, 3, 511],
+                side: 5,
+                ao: 3,
+                sky_light: 15,
+                block_light: 7,
+                layer: u16::MAX,
+                variant: 7,
+                emission: 15,
+            },
+            PackedVertex {
+                position: [0, 100, 17],
+                side: 2,
+                ao: 1,
+                sky_light: 3,
+                block_light: 0,
+                layer: 12,
+                variant: 4,
+                emission: 0,
+            },
+        ] {
+            assert_eq!(PackedVertex::unpack(vertex.pack()), vertex);
+        }
+    }
+
+    // Uvs in voxels the way `PackedVertex` tells shaders to build them
+    fn packed_uv(vertex: PackedVertex) -> [f32; 2] {
+        let position = glam::Vec3::from(vertex.position.map(|axis| axis as f32 / 16.0));
+        let (right, up) = TEXTURE_AXES[vertex.side as usize];
+        let (mut u, mut v) = (
+            position.dot(glam::IVec3::from(right).as_vec3()),
+            -position.dot(glam::IVec3::from(up).as_vec3()),
+        );
+        if vertex.variant & 4 != 0 {
+            (u, v) = (v, u);
+        }
+        if vertex.variant & 1 != 0 {
+            u = -u;
+        }
+        if vertex.variant & 2 != 0 {
+            v = -v;
+        }
+        [u, v]
+    }
+
+    // Whether the uvs of a face sample the same spots of a repeating texture. They may only differ by whole voxels
+    // that are the same for every vertex of the face
+    fn same_uvs(one: &[[f32; 2]], two: &[[f32; 2]]) -> bool {
+        let shift = [0, 1].map(|axis| one[0][axis] - two[0][axis]);
+        shift
+            .iter()
+            .all(|shift| (shift - shift.round()).abs() < 1e-4)
+            && one.iter().zip(two).all(|(one, two)| {
+                (0..2).all(|axis| (one[axis] - two[axis] - shift[axis]).abs() < 1e-4)
+            })
+    }
+
+    fn chunk() -> ChunkData<BlockData, BlockRegistry> {
+        let mut chunk = ChunkData::default();
+        for x in 0..16 {
+            for z in 0..16 {
+                chunk.set(RelativeVoxelPos::new(x, 0, z), block("stone"));
+            }
+        }
+        chunk.set(RelativeVoxelPos::new(4, 1, 4), block("slab"));
+        chunk.set(RelativeVoxelPos::new(15, 15, 15), block("stone"));
+        chunk.set(RelativeVoxelPos::new(6, 1, 6), block("glass"));
+        chunk.set(RelativeVoxelPos::new(6, 2, 6), block("glass"));
+        chunk.set_lit(true);
+        for x in 0..16 {
+            for y in 1..16 {
+                for z in 0..16 {
+                    chunk.set_sky_light(RelativeVoxelPos::new(x, y, z), y as u8);
+                    chunk.set_block_light(RelativeVoxelPos::new(x, y, z), x as u8);
+                }
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn matches_float_mesh() {
+        let (vox_registry, mut geo_registry, mut asset_registry) = registries();
+        // Slab uvs where the slab sits in the voxel so they can be packed
+        let slab = &mut geo_registry.get_mut("vinox:slab").unwrap().element.cubes[0];
+        for side in [0, 1, 4, 5] {
+            slab.uv[side] = ((0, 8), (16, 8));
+        }
+        // Full blocks get rotated and flipped textures
+        geo_registry.get_mut("vinox:block").unwrap().element.cubes[0].texture_variance = [true; 6];
+        for name in ["stone", "glass", "slab"] {
+            asset_registry
+                .texture_layers
+                .insert(format!("vinox:{name}"), [0, 1, 2, 3, 4, 5]);
+        }
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            chunk(),
+            std::array::from_fn(|_| ChunkData::default()),
+            ChunkPos::new(0, 0, 0),
+            &vox_registry,
+            &geo_registry,
+            &asset_registry,
+        );
+        for greedy in [false, true] {
+            let options = MeshOptions {
+                greedy,
+                light_mode: LightMode::Separate,
+                side_buckets: true,
+                texture_mode: TextureMode::Layers,
+                ..Default::default()
+            };
+            let float =
+                full_mesh_with_options(&asset_registry, &boundary, [0, 0, 0].into(), &options);
+            let packed = full_mesh_with_options(
+                &asset_registry,
+                &boundary,
+                [0, 0, 0].into(),
+                &MeshOptions {
+                    packed: true,
+                    ..options
+                },
+            );
+            for (float, packed) in [
+                (&float.chunk_mesh, &packed.chunk_mesh),
+                (&float.transparent_mesh, &packed.transparent_mesh),
+            ] {
+                let vertices = packed.packed.as_ref().unwrap();
+                assert!(packed.vertices.is_empty() && packed.uvs.is_none());
+                assert_eq!(vertices.len(), float.vertices.len());
+                assert_eq!(packed.indices, float.indices);
+                assert_eq!(packed.side_ranges, float.side_ranges);
+                let uvs = float.uvs.as_ref().unwrap();
+                let layers = float.texture_layers.as_ref().unwrap();
+                let ao = float.ao.as_ref().unwrap();
+                let sky = float.sky_light.as_ref().unwrap();
+                let block = float.block_light.as_ref().unwrap();
+                let unpacked = vertices
+                    .iter()
+                    .map(|vertex| PackedVertex::unpack(*vertex))
+                    .collect::<Vec<_>>();
+                for face in (0..unpacked.len()).step_by(4) {
+                    let packed_uvs = unpacked[face..face + 4]
+                        .iter()
+                        .map(|vertex| packed_uv(*vertex))
+                        .collect::<Vec<_>>();
+                    assert!(same_uvs(&packed_uvs, &uvs[face..face + 4]), "face {face}");
+                }
+                for (i, vertex) in unpacked.into_iter().enumerate() {
+                    assert_eq!(
+                        vertex.position.map(|axis| axis as f32 / 16.0),
+                        float.vertices[i]
+                    );
+                    assert_eq!(Side::from(vertex.side as usize).normal(), float.normals[i]);
+                    assert_eq!(vertex.layer as u32, layers[i]);
+                    assert_eq!(vertex.ao as f32 / 3.0, ao[i]);
+                    assert_eq!(vertex.sky_light, (sky[i] * 15.0).round() as u8);
+                    assert_eq!(vertex.block_light, (block[i] * 15.0).round() as u8);
+                }
+            }
+            assert_eq!(
+                packed.transparent_mesh.face_centers,
+                float.transparent_mesh.face_centers
+            );
+        }
+    }
+
+    #[test]
+    fn falls_back_to_floats() {
+        let (vox_registry, mut geo_registry, mut asset_registry) = registries();
+        asset_registry
+            .texture_layers
+            .insert("vinox:stone".into(), [0; 6]);
+        let mesh =
+            |geo_registry: &GeometryRegistry, asset_registry: &AssetRegistry, texture_mode| {
+                let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+                    chunk(),
+                    std::array::from_fn(|_| ChunkData::default()),
+                    ChunkPos::new(0, 0, 0),
+                    &vox_registry,
+                    geo_registry,
+                    asset_registry,
+                );
+                full_mesh_with_options(
+                    asset_registry,
+                    &boundary,
+                    [0, 0, 0].into(),
+                    &MeshOptions {
+                        packed: true,
+                        texture_mode,
+                        ..Default::default()
+                    },
+                )
+                .chunk_mesh
+            };
+        // The stone only mesh packs but the slabs sides use the whole texture squashed onto half a voxel
+        let packed = mesh(&geo_registry, &asset_registry, TextureMode::Layers);
+        assert!(packed.packed.is_none() && !packed.vertices.is_empty());
+
+        let slab = &mut geo_registry.get_mut("vinox:slab").unwrap().element.cubes[0];
+        for side in [0, 1, 4, 5] {
+            slab.uv[side] = ((0, 8), (16, 8));
+        }
+        assert!(mesh(&geo_registry, &asset_registry, TextureMode::Layers)
+            .packed
+            .is_some());
+        // Uvs from the top half of the texture don't line up with the position
+        let slab = &mut geo_registry.get_mut("vinox:slab").unwrap().element.cubes[0];
+        slab.uv[0] = ((0, 0), (16, 8));
+        assert!(mesh(&geo_registry, &asset_registry, TextureMode::Layers)
+            .packed
+            .is_none());
+        geo_registry.get_mut("vinox:slab").unwrap().element.cubes[0].uv[0] = ((0, 8), (16, 8));
+
+        // Layers that don't fit and atlas textures
+        assert!(mesh(&geo_registry, &asset_registry, TextureMode::Atlas)
+            .packed
+            .is_none());
+        asset_registry
+            .texture_layers
+            .insert("vinox:stone".into(), [u16::MAX as u32 + 1; 6]);
+        assert!(mesh(&geo_registry, &asset_registry, TextureMode::Layers)
+            .packed
+            .is_none());
+
+        // Rotated geometry
+        asset_registry
+            .texture_layers
+            .insert("vinox:stone".into(), [0; 6]);
+        geo_registry.get_mut("vinox:slab").unwrap().element.rotation = (0, 45, 0);
+        assert!(mesh(&geo_registry, &asset_registry, TextureMode::Layers)
+            .packed
+            .is_none());
+    }
+}
@@ -0,0 +1,311 @@
+use serde::Serialize;
+
+use crate::prelude::*;
+
+/// A smooth surface over the voxels marked smooth (`RenderedVoxel::is_smooth`). Every vertex blends up to four
+/// materials so shaders can mix the textures of the voxels around it
+#[derive(Default)]
+pub struct SmoothMesh {
+    pub vertices: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+    /// Indices into `materials` of the materials of every vertex. Unused slots have a weight of 0
+    pub material_ids: Vec<[u32; 4]>,
+    /// How much of each of the vertices `material_ids` to use. Adds up to 1
+    pub material_weights: Vec<[f32; 4]>,
+    /// Textures of every material used in the mesh
+    pub materials: Vec<Option<[UVRect; 6]>>,
+    /// Texture array layers of every material for `TextureMode::Layers`
+    pub material_layers: Vec<Option<[u32; 6]>>,
+    /// Brightest light around every vertex (0.0..1.0). Unlit chunks are fully bright
+    pub light: Vec<f32>,
+}
+
+/// Where a voxel is solid for the smooth surface
+fn is_solid(voxel: &RenderedBlockData) -> bool {
+    voxel.smooth && voxel.visibility != VoxelVisibility::Empty
+}
+
+/// Offsets of the 8 voxels at the corners of a cell
+const CORNERS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [0, 1, 0],
+    [1, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [0, 1, 1],
+    [1, 1, 1],
+];
+
+/// Meshes the smooth voxels of a chunk with surface nets. Every cell between 8 voxel centers that has both solid and
+/// non solid corners gets one vertex at the average of its crossing edges, and every crossing edge of the chunk gets a
+/// quad between the 4 cells around it. Cells on the border are built from the same voxels in both chunks so the
+/// surface lines up across chunks. Blocky voxels count as empty here so both kinds of voxels can share a chunk
+pub fn smooth_mesh<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    chunk: &ChunkBoundary<V, R>,
+    options: &MeshOptions,
+) -> SmoothMesh {
+    let edge = ChunkBoundary::<V, R>::edge();
+    let cells = edge - 1;
+    let voxel_size = lod_scale(options.lod) as f32;
+    let voxel = |[x, y, z]: [usize; 3]| &chunk.voxels()[ChunkBoundary::<V, R>::linearize(x, y, z)];
+
+    let mut mesh = SmoothMesh::default();
+    // Matched voxels to their index in `SmoothMesh::materials`
+    let mut material_indices: Vec<(usize, u32)> = Vec::new();
+    let mut cell_vertices = vec![u32::MAX; cells * cells * cells];
+    for z in 0..cells {
+        for y in 0..cells {
+            for x in 0..cells {
+                let corners = CORNERS.map(|[dx, dy, dz]| voxel([x + dx, y + dy, z + dz]));
+                let solid = corners.map(is_solid);
+                if solid.iter().all(|solid| *solid) || !solid.iter().any(|solid| *solid) {
+                    continue;
+                }
+
+                // Average the middle of every edge of the cell where the surface crosses
+                let mut crossings = glam::Vec3::ZERO;
+                let mut crossing_count = 0.0;
+                for (a, b) in [
+                    (0, 1),
+                    (2, 3),
+                    (4, 5),
+                    (6, 7),
+                    (0, 2),
+                    (1, 3),
+                    (4, 6),
+                    (5, 7),
+                    (0, 4),
+                    (1, 5),
+                    (2, 6),
+                    (3, 7),
+                ] {
+                    if solid[a] != solid[b] {
+                        crossings += (glam::Vec3::from(CORNERS[a].map(|axis| axis as f32))
+                            + glam::Vec3::from(CORNERS[b].map(|axis| axis as f32)))
+                            / 2.0;
+                        crossing_count += 1.0;
+                    }
+                }
+                // Boundary voxel 1 is the first voxel of the chunk and positions are at voxel centers
+                let position = (glam::Vec3::new(x as f32, y as f32, z as f32) - 0.5
+                    + crossings / crossing_count)
+                    * voxel_size;
+
+                // Points from the solid corners towards the empty ones
+                let mut gradient = glam::Vec3::ZERO;
+                for (corner, offset) in CORNERS.iter().enumerate() {
+                    if solid[corner] {
+                        gradient -= glam::Vec3::from(offset.map(|axis| axis as f32 * 2.0 - 1.0));
+                    }
+                }
+
+                let mut counts: Vec<(u32, u32)> = Vec::new();
+                for corner in corners.iter().filter(|corner| is_solid(corner)) {
+                    let material = match material_indices
+                        .iter()
+                        .find(|(match_index, _)| *match_index == corner.match_index)
+                    {
+                        Some((_, material)) => *material,
+                        None => {
+                            let material = mesh.materials.len() as u32;
+                            mesh.materials.push(corner.textures);
+                            mesh.material_layers.push(corner.texture_layers);
+                            material_indices.push((corner.match_index, material));
+                            material
+                        }
+                    };
+                    match counts.iter_mut().find(|(counted, _)| *counted == material) {
+                        Some((_, count)) => *count += 1,
+                        None => counts.push((material, 1)),
+                    }
+                }
+                counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+                counts.truncate(4);
+                let total = counts.iter().map(|(_, count)| count).sum::<u32>() as f32;
+                let mut material_ids = [0; 4];
+                let mut material_weights = [0.0; 4];
+                for (slot, (material, count)) in counts.into_iter().enumerate() {
+                    material_ids[slot] = material;
+                    material_weights[slot] = count as f32 / total;
+                }
+
+                let light = corners
+                    .iter()
+                    .filter(|corner| !is_solid(corner))
+                    .map(|corner| corner.light.unwrap_or(MAX_LIGHT))
+                    .max()
+                    .unwrap_or_default();
+
+                cell_vertices[(z * cells + y) * cells + x] = mesh.vertices.len() as u32;
+                mesh.vertices.push(position.into());
+                mesh.normals.push(gradient.normalize_or_zero().into());
+                mesh.material_ids.push(material_ids);
+                mesh.material_weights.push(material_weights);
+                mesh.light.push(light as f32 / MAX_LIGHT as f32);
+            }
+        }
+    }
+
+    // Each chunk only builds quads for the edges starting at its own voxels so borders aren't doubled
+    let cell_vertex = |[x, y, z]: [usize; 3]| cell_vertices[(z * cells + y) * cells + x];
+    for z in 1..edge - 1 {
+        for y in 1..edge - 1 {
+            for x in 1..edge - 1 {
+                let pos = [x, y, z];
+                let solid = is_solid(voxel(pos));
+                for axis in 0..3 {
+                    let (one, two) = ((axis + 1) % 3, (axis + 2) % 3);
+                    let mut next = pos;
+                    next[axis] += 1;
+                    if solid == is_solid(voxel(next)) {
+                        continue;
+                    }
+                    let offset = |one_offset: usize, two_offset: usize| {
+                        let mut cell = pos;
+                        cell[one] -= one_offset;
+                        cell[two] -= two_offset;
+                        cell_vertex(cell)
+                    };
+                    let quad = [offset(1, 1), offset(0, 1), offset(0, 0), offset(1, 0)];
+                    // Faces point away from the solid side
+                    let [a, b, c, d] = if solid {
+                        quad
+                    } else {
+                        [quad[0], quad[3], quad[2], quad[1]]
+                    };
+                    mesh.indices.extend_from_slice(&[a, b, c, a, c, d]);
+                }
+            }
+        }
+    }
+    mesh
+}
+
+#[cfg(all(test, feature = "block"))]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    // A ball of radius 10 around (16, 0, 8) that is smooth dirt left of x 16 and stone right of it
+    fn world() -> (
+        ChunkMap<BlockData, BlockRegistry>,
+        BlockRegistry,
+        GeometryRegistry,
+        AssetRegistry,
+    ) {
+        let (mut vox_registry, geo_registry, mut asset_registry) = registries();
+        vox_registry.insert(
+            "vinox:dirt".into(),
+            Block {
+                identifier: "vinox:dirt".into(),
+                geometry: Some(BlockGeometry::Block),
+                visibility: Some(VoxelVisibility::Opaque),
+                smooth: Some(true),
+                ..Default::default()
+            },
+        );
+        asset_registry.texture_uvs.insert(
+            "vinox:dirt".into(),
+            [UVRect {
+                x: 16.,
+                y: 0.,
+                w: 16.,
+                h: 16.,
+            }; 6],
+        );
+        let mut world = ChunkMap::default();
+        for x in -1..=2 {
+            for y in -1..=1 {
+                for z in -1..=1 {
+                    world.insert(ChunkPos::new(x, y, z), ChunkData::default());
+                }
+            }
+        }
+        for x in -16i32..48 {
+            for y in -16i32..16 {
+                for z in -16i32..32 {
+                    if (x - 16).pow(2) + y.pow(2) + (z - 8).pow(2) < 100 {
+                        let name = if x < 16 { "dirt" } else { "stone" };
+                        world.set_voxel(VoxelPos::new(x, y, z), block(name));
+                    }
+                }
+            }
+        }
+        (world, vox_registry, geo_registry, asset_registry)
+    }
+
+    fn mesh(
+        (world, vox_registry, geo_registry, asset_registry): &(
+            ChunkMap<BlockData, BlockRegistry>,
+            BlockRegistry,
+            GeometryRegistry,
+            AssetRegistry,
+        ),
+        pos: ChunkPos,
+    ) -> MeshedChunk {
+        let boundary = ChunkBoundary::<BlockData, BlockRegistry>::new_at(
+            world[&pos].clone(),
+            world.neighbors(pos).unwrap(),
+            pos,
+            vox_registry,
+            geo_registry,
+            asset_registry,
+        );
+        full_mesh(asset_registry, &boundary, *pos)
+    }
+
+    #[test]
+    fn surface() {
+        let world = world();
+        let meshed = mesh(&world, ChunkPos::new(0, 0, 0));
+        let smooth = &meshed.smooth_mesh;
+        assert!(!smooth.indices.is_empty());
+        assert!(smooth
+            .indices
+            .iter()
+            .all(|index| (*index as usize) < smooth.vertices.len()));
+        assert_eq!(smooth.materials.len(), 1);
+        for weights in &smooth.material_weights {
+            assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        }
+        // Triangles wind outwards like their normals
+        for triangle in smooth.indices.chunks(3) {
+            let [a, b, c] =
+                [0, 1, 2].map(|i| glam::Vec3::from(smooth.vertices[triangle[i] as usize]));
+            let normal: glam::Vec3 = triangle
+                .iter()
+                .map(|index| glam::Vec3::from(smooth.normals[*index as usize]))
+                .sum();
+            assert!((b - a).cross(c - a).dot(normal) >= 0.0);
+        }
+        // Smooth voxels aren't meshed as blocks while the stone half is
+        assert!(meshed.chunk_mesh.vertices.is_empty());
+        assert!(!mesh(&world, ChunkPos::new(1, 0, 0))
+            .chunk_mesh
+            .vertices
+            .is_empty());
+    }
+
+    #[test]
+    fn seams_line_up() {
+        let world = world();
+        let left = mesh(&world, ChunkPos::new(0, 0, 0)).smooth_mesh;
+        let right = mesh(&world, ChunkPos::new(1, 0, 0)).smooth_mesh;
+        let seam = |vertex: &&[f32; 3]| (15.4..16.6).contains(&vertex[0]);
+        let right = right
+            .vertices
+            .iter()
+            .map(|vertex| [vertex[0] + 16.0, vertex[1], vertex[2]])
+            .collect::<Vec<_>>();
+        let left = left.vertices.iter().filter(seam).collect::<Vec<_>>();
+        assert!(!left.is_empty());
+        for vertex in left {
+            assert!(right.contains(vertex), "{vertex:?}");
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use serde::Serialize;
+
+use super::voxel_geo;
+use crate::prelude::*;
+
+/// Distance kept between a moving box and whatever it collided with so it doesn't end up stuck inside it
+const SKIN: f32 = 1.0 / 1024.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: mint::Vector3<f32>,
+    pub max: mint::Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: mint::Vector3<f32>, max: mint::Vector3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_center(center: mint::Vector3<f32>, half_extents: mint::Vector3<f32>) -> Self {
+        let (center, half_extents) = (glam::Vec3::from(center), glam::Vec3::from(half_extents));
+        Self {
+            min: (center - half_extents).into(),
+            max: (center + half_extents).into(),
+        }
+    }
+
+    pub fn center(&self) -> mint::Vector3<f32> {
+        ((glam::Vec3::from(self.min) + glam::Vec3::from(self.max)) / 2.0).into()
+    }
+
+    pub fn translate(&self, offset: mint::Vector3<f32>) -> Self {
+        let offset = glam::Vec3::from(offset);
+        Self {
+            min: (glam::Vec3::from(self.min) + offset).into(),
+            max: (glam::Vec3::from(self.max) + offset).into(),
+        }
+    }
+
+    /// Grows the box in the direction of offset so it covers everything it passes through while moving
+    pub fn sweep(&self, offset: mint::Vector3<f32>) -> Self {
+        let (min, max, offset) = (
+            glam::Vec3::from(self.min),
+            glam::Vec3::from(self.max),
+            glam::Vec3::from(offset),
+        );
+        Self {
+            min: min.min(min + offset).into(),
+            max: max.max(max + offset).into(),
+        }
+    }
+
+    /// True if the boxes overlap. Boxes that only touch do not intersect
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x
+            && self.max.x > other.min.x
+            && self.min.y < other.max.y
+            && self.max.y > other.min.y
+            && self.min.z < other.max.z
+            && self.max.z > other.min.z
+    }
+
+    fn axis_min(&self, axis: usize) -> f32 {
+        glam::Vec3::from(self.min)[axis]
+    }
+
+    fn axis_max(&self, axis: usize) -> f32 {
+        glam::Vec3::from(self.max)[axis]
+    }
+
+    fn overlaps_on(&self, other: &Aabb, axis: usize) -> bool {
+        self.axis_min(axis) < other.axis_max(axis) && self.axis_max(axis) > other.axis_min(axis)
+    }
+
+    /// Shortens movement along an axis so this box stops at other
+    fn clip(&self, other: &Aabb, axis: usize, movement: f32) -> f32 {
+        let (one, two) = ((axis + 1) % 3, (axis + 2) % 3);
+        if !self.overlaps_on(other, one) || !self.overlaps_on(other, two) {
+            return movement;
+        }
+        if movement > 0.0 && self.axis_max(axis) <= other.axis_min(axis) + SKIN {
+            movement.min((other.axis_min(axis) - self.axis_max(axis) - SKIN).max(0.0))
+        } else if movement < 0.0 && self.axis_min(axis) >= other.axis_max(axis) - SKIN {
+            movement.max((other.axis_max(axis) - self.axis_min(axis) + SKIN).min(0.0))
+        } else {
+            movement
+        }
+    }
+}
+
+/// A voxel the moving box ran into
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact {
+    pub voxel: VoxelPos,
+    /// The side of the voxel that was touched
+    pub side: Side,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveResult {
+    /// The box after moving
+    pub aabb: Aabb,
+    /// How far the box actually moved
+    pub movement: mint::Vector3<f32>,
+    pub contacts: Vec<Contact>,
+    /// True if there is something directly below the box after moving
+    pub on_ground: bool,
+    /// How high the box stepped up onto something (ie a slab or stair) if it did
+    pub stepped: Option<f32>,
+}
+
+/// Gathers the collision boxes of every voxel near the given box. Solid voxels use their `BlockGeo` cubes
+/// (`origin`/`end` are in 1/16 of a voxel) so slabs and stairs collide where they are drawn. Rotated cubes use the bounds of the rotated cube.
+/// Passable voxels are skipped and voxels with a custom collision use those boxes instead
+pub fn collision_boxes<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &ChunkMap<V, R>,
+    aabb: &Aabb,
+    vox_registry: &R,
+    geo_registry: &GeometryRegistry,
+) -> Vec<(VoxelPos, Aabb)> {
+    let min = glam::Vec3::from(aabb.min).floor().as_ivec3();
+    let max = glam::Vec3::from(aabb.max).floor().as_ivec3();
+    let mut boxes = Vec::new();
+    // Look one voxel further down for geometry taller than a voxel such as fences
+    for x in min.x..=max.x {
+        for y in min.y - 1..=max.y {
+            for z in min.z..=max.z {
+                let pos = VoxelPos::new(x, y, z);
+                let Some(voxel) = world.get_voxel(pos) else {
+                    continue;
+                };
+                let offset = glam::Vec3::new(x as f32, y as f32, z as f32);
+                let cube_boxes = match voxel.physics(Some(vox_registry)).collision {
+                    BlockCollision::Passable => continue,
+                    BlockCollision::Custom(cubes) => cubes
+                        .iter()
+                        .map(|collision_box| {
+                            let (origin, end) = (collision_box.origin, collision_box.end);
+                            (
+                                glam::Vec3::new(origin.0 as f32, origin.1 as f32, origin.2 as f32)
+                                    / 16.0,
+                                glam::Vec3::new(end.0 as f32, end.1 as f32, end.2 as f32) / 16.0,
+                            )
+                        })
+                        .collect::<Vec<_>>(),
+                    BlockCollision::Solid => {
+                        let Some(geo) = voxel_geo(&voxel, pos, vox_registry, geo_registry) else {
+                            continue;
+                        };
+                        geo.cubes
+                            .iter()
+                            .enumerate()
+                            .map(|(cube_num, cube)| {
+                                if geo.is_rotated(cube_num) {
+                                    rotated_bounds(&geo, cube_num, cube)
+                                } else {
+                                    (cube.min(), cube.max())
+                                }
+                            })
+                            .collect::<Vec<_>>()
+                    }
+                };
+                for (cube_min, cube_max) in cube_boxes {
+                    let cube_box =
+                        Aabb::new((cube_min + offset).into(), (cube_max + offset).into());
+                    if cube_box.intersects(aabb) {
+                        boxes.push((pos, cube_box));
+                    }
+                }
+            }
+        }
+    }
+    boxes
+}
+
+/// Moves a box through the world resolving collisions one axis at a time (Y then X then Z).
+/// If horizontal movement is blocked while on the ground the box tries to step up by at most step_height
+pub fn move_aabb<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &ChunkMap<V, R>,
+    aabb: &Aabb,
+    movement: mint::Vector3<f32>,
+    step_height: f32,
+    vox_registry: &R,
+    geo_registry: &GeometryRegistry,
+) -> MoveResult {
+    let wanted = glam::Vec3::from(movement);
+    let sweep = aabb
+        .sweep(movement)
+        .sweep(glam::Vec3::new(0.0, step_height.max(0.0) + SKIN, 0.0).into())
+        .sweep(glam::Vec3::new(0.0, -SKIN * 2.0, 0.0).into());
+    let boxes = collision_boxes(world, &sweep, vox_registry, geo_registry);
+
+    let (mut moved, mut contacts) = resolve(aabb, wanted, &[1, 0, 2], &boxes);
+    let mut stepped = None;
+
+    let blocked_horizontal =
+        (moved.x - wanted.x).abs() > f32::EPSILON || (moved.z - wanted.z).abs() > f32::EPSILON;
+    let was_grounded = wanted.y <= 0.0 && grounded(aabb, &boxes);
+    if step_height > 0.0 && blocked_horizontal && was_grounded {
+        // Move up, across, then back down and keep it if we got further
+        let (up, _) = resolve(aabb, glam::Vec3::new(0.0, step_height, 0.0), &[1], &boxes);
+        let raised = aabb.translate(up.into());
+        let (across, across_contacts) = resolve(
+            &raised,
+            glam::Vec3::new(wanted.x, 0.0, wanted.z),
+            &[0, 2],
+            &boxes,
+        );
+        let shifted = raised.translate(across.into());
+        let (down, down_contacts) = resolve(
+            &shifted,
+            glam::Vec3::new(0.0, -up.y + wanted.y.min(0.0), 0.0),
+            &[1],
+            &boxes,
+        );
+        let step_total = up + across + down;
+        if step_total.x * step_total.x + step_total.z * step_total.z
+            > moved.x * moved.x + moved.z * moved.z + f32::EPSILON
+        {
+            if step_total.y > SKIN {
+                stepped = Some(step_total.y);
+            }
+            moved = step_total;
+            contacts = across_contacts;
+            contacts.extend(down_contacts);
+        }
+    }
+
+    let moved_box = aabb.translate(moved.into());
+    MoveResult {
+        aabb: moved_box,
+        movement: moved.into(),
+        contacts,
+        on_ground: grounded(&moved_box, &boxes),
+        stepped,
+    }
+}
+
+/// Applies movement one axis at a time in the given order clipping against every box
+fn resolve(
+    aabb: &Aabb,
+    movement: glam::Vec3,
+    order: &[usize],
+    boxes: &[(VoxelPos, Aabb)],
+) -> (glam::Vec3, Vec<Contact>) {
+    let mut current = *aabb;
+    let mut moved = glam::Vec3::ZERO;
+    let mut contacts = Vec::new();
+    for &axis in order {
+        let wanted = movement[axis];
+        if wanted == 0.0 {
+            continue;
+        }
+        let mut clipped = wanted;
+        for (_, other) in boxes {
+            clipped = current.clip(other, axis, clipped);
+        }
+        if clipped != wanted {
+            // Record every box we are now resting against on this axis
+            for (voxel, other) in boxes {
+                if current.clip(other, axis, wanted) == clipped {
+                    contacts.push(Contact {
+                        voxel: *voxel,
+                        side: Side::new(
+                            match axis {
+                                0 => Axis::X,
+                                1 => Axis::Y,
+                                _ => Axis::Z,
+                            },
+                            wanted < 0.0,
+                        ),
+                    });
+                }
+            }
+        }
+        let mut offset = glam::Vec3::ZERO;
+        offset[axis] = clipped;
+        current = current.translate(offset.into());
+        moved[axis] = clipped;
+    }
+    (moved, contacts)
+}
+
+fn grounded(aabb: &Aabb, boxes: &[(VoxelPos, Aabb)]) -> bool {
+    let probe = aabb.translate(glam::Vec3::new(0.0, -SKIN * 2.0, 0.0).into());
+    boxes.iter().any(|(_, other)| probe.intersects(other))
+}
+
+/// Axis aligned bounds of a rotated cube in block space
+fn rotated_bounds(
+    geo: &BlockGeo,
+    cube_num: usize,
+    cube: &FaceDescript,
+) -> (glam::Vec3, glam::Vec3) {
+    let (min, max) = (cube.min(), cube.max());
+    let mut bounds_min = glam::Vec3::splat(f32::INFINITY);
+    let mut bounds_max = glam::Vec3::splat(f32::NEG_INFINITY);
+    for corner in 0..8 {
+        let point = glam::Vec3::new(
+            if corner & 1 == 0 { min.x } else { max.x },
+            if corner & 2 == 0 { min.y } else { max.y },
+            if corner & 4 == 0 { min.z } else { max.z },
+        );
+        let point = geo.rotate_point(cube_num, point);
+        bounds_min = bounds_min.min(point);
+        bounds_max = bounds_max.max(point);
+    }
+    (bounds_min, bounds_max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn world() -> ChunkMap<BlockData, BlockRegistry> {
+        let mut world = ChunkMap::default();
+        world.insert(ChunkPos::new(0, 0, 0), ChunkData::default());
+        for x in 0..16 {
+            for z in 0..16 {
+                world.set_voxel(VoxelPos::new(x, 0, z), block("stone"));
+            }
+        }
+        world.set_voxel(VoxelPos::new(6, 1, 5), block("slab"));
+        world.set_voxel(VoxelPos::new(6, 1, 8), block("stone"));
+        world
+    }
+
+    fn player(center: [f32; 3]) -> Aabb {
+        Aabb::from_center(center.into(), [0.3, 0.9, 0.3].into())
+    }
+
+    #[test]
+    fn lands_on_floor() {
+        let (vox_registry, geo_registry, _) = registries();
+        let moved = move_aabb(
+            &world(),
+            &player([5.5, 2.0, 5.5]),
+            [0.0, -2.0, 0.0].into(),
+            0.6,
+            &vox_registry,
+            &geo_registry,
+        );
+        assert!(moved.on_ground);
+        assert!((moved.aabb.min.y - 1.0).abs() < 0.01);
+        assert!(moved
+            .contacts
+            .iter()
+            .any(|contact| contact.side == Side::new(Axis::Y, true)));
+    }
+
+    #[test]
+    fn steps_onto_slab() {
+        let (vox_registry, geo_registry, _) = registries();
+        let standing = player([5.5, 1.9, 5.5]);
+        let moved = move_aabb(
+            &world(),
+            &standing,
+            [0.5, -0.1, 0.0].into(),
+            0.6,
+            &vox_registry,
+            &geo_registry,
+        );
+        assert!(moved.stepped.is_some());
+        assert!((moved.aabb.min.y - 1.5).abs() < 0.01);
+
+        // A full block is too tall to step onto
+        let moved = move_aabb(
+            &world(),
+            &player([6.5, 1.9011, 7.0]),
+            [0.0, -0.1, 1.0].into(),
+            0.6,
+            &vox_registry,
+            &geo_registry,
+        );
+        assert!(moved.stepped.is_none());
+        assert!((moved.aabb.max.z - 8.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,31 @@
+pub mod collision;
+pub mod raycast;
+
+use serde::Serialize;
+
+use crate::prelude::*;
+
+/// Looks up the geometry a voxel at pos is rendered with, including the variant it picks there
+pub(crate) fn voxel_geo<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    voxel: &V,
+    pos: VoxelPos,
+    vox_registry: &R,
+    geo_registry: &GeometryRegistry,
+) -> Option<BlockGeo> {
+    let mut geo_pal = GeoPalette::default();
+    let variant = pick_variant(*pos, &voxel.variant_weights(Some(vox_registry)));
+    let geo_index = voxel.to_geo_idx_variant(
+        Some(&mut geo_pal),
+        Some(geo_registry),
+        Some(vox_registry),
+        variant,
+    )?;
+    if geo_index < geo_pal.palette.len() {
+        Some(geo_pal.palette.swap_remove(geo_index))
+    } else {
+        None
+    }
+}
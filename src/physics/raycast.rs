@@ -0,0 +1,326 @@
+use serde::Serialize;
+
+use super::voxel_geo;
+use crate::prelude::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RaycastHit {
+    /// The voxel that was hit
+    pub voxel: VoxelPos,
+    /// The side of the voxel the ray entered through
+    pub side: Side,
+    /// Exact point in world space where the ray hit
+    pub point: mint::Vector3<f32>,
+    /// Distance from the ray origin to the hit point
+    pub distance: f32,
+}
+
+/// Walks a ray through the world one voxel at a time (DDA) and returns the first non empty voxel it hits.
+/// If a geometry registry is given the ray is tested against the voxels `BlockGeo` cuboids so slabs, stairs and crosses
+/// are only hit where they are actually drawn. Voxels where `skip` returns true are passed through (ie fluids).
+/// Voxels in chunks that aren't loaded are treated as empty
+pub fn raycast<
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R>,
+    R: VoxRegistry<V> + Clone + Default,
+>(
+    world: &ChunkMap<V, R>,
+    origin: mint::Vector3<f32>,
+    direction: mint::Vector3<f32>,
+    max_distance: f32,
+    vox_registry: &R,
+    geo_registry: Option<&GeometryRegistry>,
+    skip: impl Fn(&V) -> bool,
+) -> Option<RaycastHit> {
+    let origin = glam::Vec3::from(origin);
+    let direction = glam::Vec3::from(direction).normalize_or_zero();
+    if direction == glam::Vec3::ZERO {
+        return None;
+    }
+
+    let mut voxel = origin.floor().as_ivec3();
+    let step = direction.signum().as_ivec3();
+    // Distance along the ray needed to cross one whole voxel on each axis
+    let delta = (1.0 / direction).abs();
+    // Distance along the ray to the first voxel boundary on each axis
+    let mut next = glam::Vec3::new(
+        first_boundary(origin.x, direction.x, delta.x),
+        first_boundary(origin.y, direction.y, delta.y),
+        first_boundary(origin.z, direction.z, delta.z),
+    );
+    // The side of the current voxel the ray entered through. The voxel containing the origin has no entry side
+    // so use the side facing back along the dominant direction
+    let mut side = entry_side(dominant_axis(direction), direction);
+    let mut distance = 0.0;
+
+    while distance <= max_distance {
+        let pos = VoxelPos(voxel.into());
+        if let Some(hit_voxel) = world.get_voxel(pos) {
+            if !hit_voxel.is_empty(Some(vox_registry)) && !skip(&hit_voxel) {
+                let hit = if let Some(geo_registry) = geo_registry {
+                    voxel_geo(&hit_voxel, pos, vox_registry, geo_registry)
+                        .and_then(|geo| geo_hit(&geo, voxel.as_vec3(), origin, direction))
+                } else {
+                    Some((distance, side))
+                };
+                if let Some((hit_distance, hit_side)) = hit {
+                    if hit_distance <= max_distance {
+                        return Some(RaycastHit {
+                            voxel: pos,
+                            side: hit_side,
+                            point: (origin + direction * hit_distance).into(),
+                            distance: hit_distance,
+                        });
+                    }
+                }
+            }
+        }
+
+        let axis = if next.x < next.y && next.x < next.z {
+            0
+        } else if next.y < next.z {
+            1
+        } else {
+            2
+        };
+        distance = next[axis];
+        next[axis] += delta[axis];
+        voxel[axis] += step[axis];
+        side = entry_side(axis, direction);
+    }
+    None
+}
+
+fn first_boundary(origin: f32, direction: f32, delta: f32) -> f32 {
+    if direction > 0.0 {
+        (origin.floor() + 1.0 - origin) * delta
+    } else if direction < 0.0 {
+        (origin - origin.floor()) * delta
+    } else {
+        f32::INFINITY
+    }
+}
+
+fn dominant_axis(direction: glam::Vec3) -> usize {
+    let abs = direction.abs();
+    if abs.x >= abs.y && abs.x >= abs.z {
+        0
+    } else if abs.y >= abs.z {
+        1
+    } else {
+        2
+    }
+}
+
+/// The side of a voxel a ray travelling along direction enters through when crossing the given axis
+fn entry_side(axis: usize, direction: glam::Vec3) -> Side {
+    let axis_enum = match axis {
+        0 => Axis::X,
+        1 => Axis::Y,
+        _ => Axis::Z,
+    };
+    Side::new(axis_enum, direction[axis] < 0.0)
+}
+
+/// Tests the ray against every cube of the geometry placed at voxel. Returns the closest hit distance and side
+fn geo_hit(
+    geo: &BlockGeo,
+    voxel: glam::Vec3,
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+) -> Option<(f32, Side)> {
+    let mut closest: Option<(f32, Side)> = None;
+    for (cube_num, cube) in geo.cubes.iter().enumerate() {
+        let (local_origin, local_direction) = if geo.is_rotated(cube_num) {
+            (
+                geo.unrotate_point(cube_num, origin - voxel),
+                geo.unrotate_direction(cube_num, direction),
+            )
+        } else {
+            (origin - voxel, direction)
+        };
+        let Some((distance, axis)) = ray_box(local_origin, local_direction, cube.min(), cube.max())
+        else {
+            continue;
+        };
+        if closest.is_some_and(|(closest, _)| closest <= distance) {
+            continue;
+        }
+
+        let side = if geo.is_rotated(cube_num) {
+            // Bring the cube face normal back into world space and use the closest axis aligned side
+            let mut normal = glam::Vec3::ZERO;
+            normal[axis] = -local_direction[axis].signum();
+            let normal = geo.rotate_direction(cube_num, normal);
+            let axis = dominant_axis(normal);
+            entry_side(axis, -normal)
+        } else {
+            entry_side(axis, direction)
+        };
+        closest = Some((distance, side));
+    }
+    closest
+}
+
+/// Slab test of a ray against an axis aligned box. Returns the entry distance and the axis of the face that was entered
+fn ray_box(
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    min: glam::Vec3,
+    max: glam::Vec3,
+) -> Option<(f32, usize)> {
+    let mut t_min = f32::NEG_INFINITY;
+    let mut t_max = f32::INFINITY;
+    let mut axis = dominant_axis(direction);
+    for i in 0..3 {
+        if direction[i] == 0.0 {
+            if origin[i] < min[i] || origin[i] > max[i] {
+                return None;
+            }
+            continue;
+        }
+        let t_one = (min[i] - origin[i]) / direction[i];
+        let t_two = (max[i] - origin[i]) / direction[i];
+        let (near, far) = if t_one < t_two {
+            (t_one, t_two)
+        } else {
+            (t_two, t_one)
+        };
+        if near > t_min {
+            t_min = near;
+            axis = i;
+        }
+        t_max = t_max.min(far);
+    }
+    if t_min > t_max || t_max < 0.0 {
+        None
+    } else {
+        Some((t_min.max(0.0), axis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn world() -> ChunkMap<BlockData, BlockRegistry> {
+        let mut world = ChunkMap::default();
+        world.insert(ChunkPos::new(0, 0, 0), ChunkData::default());
+        world.insert(ChunkPos::new(-1, 0, 0), ChunkData::default());
+        world.set_voxel(VoxelPos::new(5, 2, 3), block("stone"));
+        world.set_voxel(VoxelPos::new(-3, 2, 3), block("slab"));
+        world
+    }
+
+    #[test]
+    fn hits_first_voxel() {
+        let (vox_registry, _, _) = registries();
+        let hit = raycast(
+            &world(),
+            [0.5, 2.5, 3.5].into(),
+            [1., 0., 0.].into(),
+            20.,
+            &vox_registry,
+            None,
+            |_| false,
+        )
+        .unwrap();
+        assert_eq!(hit.voxel, VoxelPos::new(5, 2, 3));
+        assert_eq!(hit.side, Side::new(Axis::X, false));
+        assert!((hit.distance - 4.5).abs() < 1e-4);
+        assert!((hit.point.x - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn crosses_chunks() {
+        let (vox_registry, _, _) = registries();
+        let hit = raycast(
+            &world(),
+            [5.5, 2.5, 3.5].into(),
+            [-1., 0., 0.].into(),
+            20.,
+            &vox_registry,
+            None,
+            |_| false,
+        );
+        // Starting inside the stone hits it right away
+        assert_eq!(hit.unwrap().voxel, VoxelPos::new(5, 2, 3));
+
+        let hit = raycast(
+            &world(),
+            [4.5, 2.5, 3.5].into(),
+            [-1., 0., 0.].into(),
+            20.,
+            &vox_registry,
+            None,
+            |_| false,
+        )
+        .unwrap();
+        assert_eq!(hit.voxel, VoxelPos::new(-3, 2, 3));
+        assert_eq!(hit.side, Side::new(Axis::X, true));
+        assert!((hit.distance - 6.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn max_distance_and_skip() {
+        let (vox_registry, _, _) = registries();
+        let world = world();
+        let cast = |max_distance, skip: fn(&BlockData) -> bool| {
+            raycast(
+                &world,
+                [0.5, 2.5, 3.5].into(),
+                [1., 0., 0.].into(),
+                max_distance,
+                &vox_registry,
+                None,
+                skip,
+            )
+        };
+        assert!(cast(4.0, |_| false).is_none());
+        assert!(cast(20.0, |voxel| voxel.identifier == "vinox:stone").is_none());
+    }
+
+    #[test]
+    fn uses_geometry() {
+        let (vox_registry, geo_registry, _) = registries();
+        let world = world();
+        // Passes over the top half of the slab that isn't drawn
+        let hit = raycast(
+            &world,
+            [-2.5, 2.75, 3.5].into(),
+            [-1., 0., 0.].into(),
+            20.,
+            &vox_registry,
+            Some(&geo_registry),
+            |_| false,
+        );
+        assert!(hit.is_none());
+
+        let hit = raycast(
+            &world,
+            [-2.5, 5., 3.5].into(),
+            [-0.1, -1., 0.].into(),
+            20.,
+            &vox_registry,
+            Some(&geo_registry),
+            |_| false,
+        )
+        .unwrap();
+        assert_eq!(hit.voxel, VoxelPos::new(-3, 2, 3));
+        assert_eq!(hit.side, Side::new(Axis::Y, true));
+        assert!((hit.point.y - 2.5).abs() < 1e-4);
+
+        // Without geometry the whole voxel is solid
+        let hit = raycast(
+            &world,
+            [-2.55, 5., 3.5].into(),
+            [0., -1., 0.].into(),
+            20.,
+            &vox_registry,
+            None,
+            |_| false,
+        )
+        .unwrap();
+        assert!((hit.point.y - 3.0).abs() < 1e-4);
+    }
+}
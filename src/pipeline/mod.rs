@@ -0,0 +1,579 @@
+pub mod pool;
+
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use ahash::{HashMap, HashMapExt};
+use serde::Serialize;
+
+use crate::prelude::*;
+use pool::WorkerPool;
+
+/// The lifecycle of a chunk. A chunk can only move into a stage once all 26 of its neighbors have reached
+/// that stages `neighbor_requirement`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ChunkStage {
+    #[default]
+    Empty,
+    Generated,
+    Decorated,
+    Lit,
+    Meshed,
+}
+
+impl ChunkStage {
+    pub fn next(&self) -> Option<ChunkStage> {
+        match self {
+            ChunkStage::Empty => Some(ChunkStage::Generated),
+            ChunkStage::Generated => Some(ChunkStage::Decorated),
+            ChunkStage::Decorated => Some(ChunkStage::Lit),
+            ChunkStage::Lit => Some(ChunkStage::Meshed),
+            ChunkStage::Meshed => None,
+        }
+    }
+
+    /// The stage every neighbor must have reached before a chunk can advance into this stage
+    pub fn neighbor_requirement(&self) -> Option<ChunkStage> {
+        match self {
+            ChunkStage::Empty | ChunkStage::Generated => None,
+            ChunkStage::Decorated => Some(ChunkStage::Generated),
+            ChunkStage::Lit => Some(ChunkStage::Decorated),
+            ChunkStage::Meshed => Some(ChunkStage::Lit),
+        }
+    }
+}
+
+/// Implement this to provide the work done by each stage of the pipeline. Meshing is handled by the pipeline itself.
+/// Neighbors are passed in the order of `ChunkPos::neighbors` and are copies so only the center chunk can be modified
+pub trait ChunkStages<V: Voxel<R>, R: VoxRegistry<V>>: Send + Sync {
+    fn generate(&self, pos: ChunkPos) -> ChunkData<V, R>;
+
+    fn decorate(
+        &self,
+        _pos: ChunkPos,
+        _chunk: &mut ChunkData<V, R>,
+        _neighbors: &[ChunkData<V, R>; 26],
+    ) {
+    }
+
+    fn light(
+        &self,
+        _pos: ChunkPos,
+        _chunk: &mut ChunkData<V, R>,
+        _neighbors: &[ChunkData<V, R>; 26],
+    ) {
+    }
+}
+
+struct PipelineEntry {
+    stage: ChunkStage,
+    target: ChunkStage,
+    busy: bool,
+    // Set when a stage panicked. The chunk isn't scheduled again until it is requested or invalidated
+    failed: bool,
+    // Set when the chunk is invalidated while a job for it is running
+    invalidated: Option<ChunkStage>,
+    // Unique per entry so results of jobs started before a chunk was removed and requested again are dropped
+    generation: u64,
+}
+
+struct JobResult<V: Voxel<R>, R: VoxRegistry<V>> {
+    pos: ChunkPos,
+    generation: u64,
+    output: JobOutput<V, R>,
+}
+
+enum JobOutput<V: Voxel<R>, R: VoxRegistry<V>> {
+    Chunk(ChunkStage, Box<ChunkData<V, R>>),
+    Mesh(Box<MeshedChunk>),
+    Failed,
+}
+
+/// Drives chunks through generation, decoration, lighting and meshing on a worker pool.
+/// Call `update` regularly to schedule ready chunks and to collect finished meshes
+pub struct ChunkPipeline<V, R, S>
+where
+    V: Voxel<R> + Send + 'static,
+    R: VoxRegistry<V> + Send + 'static,
+{
+    chunks: ChunkMap<V, R>,
+    entries: HashMap<ChunkPos, PipelineEntry>,
+    stages: Arc<S>,
+    vox_registry: Arc<R>,
+    geo_registry: Arc<GeometryRegistry>,
+    asset_registry: Arc<AssetRegistry>,
+    mesh_options: MeshOptions,
+    pool: WorkerPool<JobResult<V, R>>,
+    next_generation: u64,
+}
+
+impl<V, R, S> ChunkPipeline<V, R, S>
+where
+    V: Voxel<R> + Clone + Serialize + Eq + Default + RenderedVoxel<V, R> + Send + Sync + 'static,
+    R: VoxRegistry<V> + Clone + Default + Send + Sync + 'static,
+    S: ChunkStages<V, R> + 'static,
+{
+    /// A pipeline with 0 workers runs every stage inline during `update`
+    pub fn new(
+        stages: S,
+        vox_registry: R,
+        geo_registry: GeometryRegistry,
+        asset_registry: AssetRegistry,
+        workers: usize,
+    ) -> Self {
+        Self {
+            chunks: ChunkMap::default(),
+            entries: HashMap::new(),
+            stages: Arc::new(stages),
+            vox_registry: Arc::new(vox_registry),
+            geo_registry: Arc::new(geo_registry),
+            asset_registry: Arc::new(asset_registry),
+            mesh_options: MeshOptions::default(),
+            pool: WorkerPool::new(workers),
+            next_generation: 0,
+        }
+    }
+
+    fn entry(&mut self, stage: ChunkStage, target: ChunkStage) -> PipelineEntry {
+        self.next_generation += 1;
+        PipelineEntry {
+            stage,
+            target,
+            busy: false,
+            failed: false,
+            invalidated: None,
+            generation: self.next_generation,
+        }
+    }
+
+    /// Asks for a chunk to be advanced up to the target stage. Chunks on the edge of the loaded area usually only need
+    /// to be generated or decorated so their neighbors can advance further. Requesting a chunk whose stage failed retries it
+    pub fn request(&mut self, pos: ChunkPos, target: ChunkStage) {
+        if !self.entries.contains_key(&pos) {
+            let entry = self.entry(ChunkStage::Empty, target);
+            self.entries.insert(pos, entry);
+        }
+        let entry = self.entries.get_mut(&pos).unwrap();
+        entry.target = entry.target.max(target);
+        entry.failed = false;
+    }
+
+    /// Inserts an already generated (or loaded) chunk at the given stage
+    pub fn insert(
+        &mut self,
+        pos: ChunkPos,
+        chunk: ChunkData<V, R>,
+        stage: ChunkStage,
+        target: ChunkStage,
+    ) {
+        self.chunks.insert(pos, chunk);
+        let entry = self.entry(stage, target.max(stage));
+        self.entries.insert(pos, entry);
+    }
+
+    /// Drops a chunk from the pipeline. Jobs already running for it are discarded when they finish
+    pub fn remove(&mut self, pos: ChunkPos) -> Option<ChunkData<V, R>> {
+        self.entries.remove(&pos);
+        self.chunks.remove(&pos)
+    }
+
+    /// Moves a chunk back to an earlier stage. For example after a block changed use `ChunkStage::Decorated` to relight and remesh it.
+    /// If a job is running for the chunk its result is thrown away so edits made in the meantime are kept
+    pub fn invalidate(&mut self, pos: ChunkPos, stage: ChunkStage) {
+        if let Some(entry) = self.entries.get_mut(&pos) {
+            if entry.busy {
+                entry.invalidated = Some(entry.invalidated.unwrap_or(stage).min(stage));
+            }
+            entry.stage = entry.stage.min(stage);
+            entry.failed = false;
+        }
+    }
+
+    /// Options used for every mesh built after this call. Already meshed chunks keep their old mesh until invalidated
+    pub fn set_mesh_options(&mut self, options: MeshOptions) {
+        self.mesh_options = options;
+    }
+
+    pub fn mesh_options(&self) -> &MeshOptions {
+        &self.mesh_options
+    }
+
+    pub fn stage(&self, pos: ChunkPos) -> ChunkStage {
+        self.entries
+            .get(&pos)
+            .map(|entry| entry.stage)
+            .unwrap_or_default()
+    }
+
+    /// True when a stage panicked for this chunk. It stays at its last finished stage until requested or invalidated again
+    pub fn has_failed(&self, pos: ChunkPos) -> bool {
+        self.entries.get(&pos).is_some_and(|entry| entry.failed)
+    }
+
+    pub fn chunk(&self, pos: ChunkPos) -> Option<&ChunkData<V, R>> {
+        self.chunks.get(&pos)
+    }
+
+    pub fn chunk_mut(&mut self, pos: ChunkPos) -> Option<&mut ChunkData<V, R>> {
+        self.chunks.get_mut(&pos)
+    }
+
+    pub fn chunks(&self) -> &ChunkMap<V, R> {
+        &self.chunks
+    }
+
+    /// True when there are no jobs running and no chunk can advance any further
+    pub fn is_idle(&self) -> bool {
+        self.pool.pending() == 0 && self.ready().is_empty()
+    }
+
+    /// Collects finished jobs then schedules every chunk whose neighbors are ready. Returns meshes that finished since the last call
+    pub fn update(&mut self) -> Vec<MeshedChunk> {
+        let mut meshes = Vec::new();
+        for result in self.pool.drain() {
+            self.finish(result, &mut meshes);
+        }
+
+        for (pos, next) in self.ready() {
+            self.schedule(pos, next);
+        }
+
+        // Inline pools finish jobs as soon as they are spawned
+        for result in self.pool.drain() {
+            self.finish(result, &mut meshes);
+        }
+        meshes
+    }
+
+    /// Runs `update` until every requested chunk reached its target stage or can't advance any further
+    pub fn flush(&mut self) -> Vec<MeshedChunk> {
+        let mut meshes = self.update();
+        while !self.is_idle() {
+            if let Some(result) = self.pool.recv() {
+                self.finish(result, &mut meshes);
+            }
+            meshes.extend(self.update());
+        }
+        meshes
+    }
+
+    fn ready(&self) -> Vec<(ChunkPos, ChunkStage)> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| !entry.busy && !entry.failed && entry.stage < entry.target)
+            .filter_map(|(pos, entry)| {
+                let next = entry.stage.next()?;
+                if let Some(requirement) = next.neighbor_requirement() {
+                    let ready = pos.neighbors().iter().all(|neighbor| {
+                        self.entries
+                            .get(neighbor)
+                            .is_some_and(|neighbor| neighbor.stage >= requirement)
+                    });
+                    if !ready {
+                        return None;
+                    }
+                }
+                Some((*pos, next))
+            })
+            .collect()
+    }
+
+    fn schedule(&mut self, pos: ChunkPos, next: ChunkStage) {
+        let Some(entry) = self.entries.get_mut(&pos) else {
+            return;
+        };
+        entry.busy = true;
+        let generation = entry.generation;
+
+        let stages = self.stages.clone();
+        match next {
+            ChunkStage::Empty => {}
+            ChunkStage::Generated => self.spawn(pos, generation, move || {
+                JobOutput::Chunk(ChunkStage::Generated, Box::new(stages.generate(pos)))
+            }),
+            ChunkStage::Decorated | ChunkStage::Lit => {
+                let mut chunk = self.chunks.get(&pos).cloned().unwrap_or_default();
+                let neighbors = self.chunks.neighbors_or_default(pos);
+                self.spawn(pos, generation, move || {
+                    if next == ChunkStage::Decorated {
+                        stages.decorate(pos, &mut chunk, &neighbors);
+                    } else {
+                        stages.light(pos, &mut chunk, &neighbors);
+                    }
+                    JobOutput::Chunk(next, Box::new(chunk))
+                })
+            }
+            ChunkStage::Meshed => {
+                let chunk = self.chunks.get(&pos).cloned().unwrap_or_default();
+                let neighbors = self.chunks.neighbors_or_default(pos);
+                let vox_registry = self.vox_registry.clone();
+                let geo_registry = self.geo_registry.clone();
+                let asset_registry = self.asset_registry.clone();
+                let options = self.mesh_options;
+                self.spawn(pos, generation, move || {
+                    let boundary = ChunkBoundary::<V, R>::new_at(
+                        chunk,
+                        neighbors,
+                        pos,
+                        &vox_registry,
+                        &geo_registry,
+                        &asset_registry,
+                    );
+                    JobOutput::Mesh(Box::new(full_mesh_with_options(
+                        &asset_registry,
+                        &boundary,
+                        *pos,
+                        &options,
+                    )))
+                })
+            }
+        }
+    }
+
+    // Stage code is user provided so a panic is caught here where the chunk it belongs to is still known
+    fn spawn(
+        &mut self,
+        pos: ChunkPos,
+        generation: u64,
+        job: impl FnOnce() -> JobOutput<V, R> + Send + 'static,
+    ) {
+        self.pool.spawn(move || JobResult {
+            pos,
+            generation,
+            output: catch_unwind(AssertUnwindSafe(job)).unwrap_or(JobOutput::Failed),
+        });
+    }
+
+    fn finish(
+        &mut self,
+        result: std::thread::Result<JobResult<V, R>>,
+        meshes: &mut Vec<MeshedChunk>,
+    ) {
+        // Every job catches its own panic so the pool never hands back an error
+        let Ok(JobResult {
+            pos,
+            generation,
+            output,
+        }) = result
+        else {
+            return;
+        };
+        // The chunk may have been removed, or removed and requested again, while the job was running
+        let Some(entry) = self
+            .entries
+            .get_mut(&pos)
+            .filter(|entry| entry.generation == generation)
+        else {
+            return;
+        };
+        entry.busy = false;
+        if let Some(invalidated) = entry.invalidated.take() {
+            entry.stage = entry.stage.min(invalidated);
+            return;
+        }
+        match output {
+            JobOutput::Chunk(stage, chunk) => {
+                entry.stage = stage;
+                self.chunks.insert(pos, *chunk);
+            }
+            JobOutput::Mesh(mesh) => {
+                entry.stage = ChunkStage::Meshed;
+                meshes.push(*mesh);
+            }
+            JobOutput::Failed => entry.failed = true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::test_util::*;
+
+    const GATED: ChunkPos = ChunkPos(mint::Vector3 { x: 10, y: 0, z: 0 });
+
+    #[derive(Default)]
+    struct Stages {
+        // Decorating `GATED` blocks until this is set
+        open: Arc<AtomicBool>,
+        panic_at: Option<ChunkPos>,
+    }
+
+    impl ChunkStages<BlockData, BlockRegistry> for Stages {
+        fn generate(&self, pos: ChunkPos) -> ChunkData<BlockData, BlockRegistry> {
+            if Some(pos) == self.panic_at {
+                panic!("generation failed");
+            }
+            let mut chunk = ChunkData::default();
+            if pos.y < 0 {
+                chunk.set(RelativeVoxelPos::new(0, 0, 0), block("stone"));
+            }
+            chunk
+        }
+
+        fn decorate(
+            &self,
+            pos: ChunkPos,
+            _chunk: &mut ChunkData<BlockData, BlockRegistry>,
+            _neighbors: &[ChunkData<BlockData, BlockRegistry>; 26],
+        ) {
+            while pos == GATED && !self.open.load(Ordering::Acquire) {
+                std::thread::yield_now();
+            }
+        }
+    }
+
+    fn pipeline(stages: Stages, workers: usize) -> ChunkPipeline<BlockData, BlockRegistry, Stages> {
+        let (vox_registry, geo_registry, asset_registry) = registries();
+        ChunkPipeline::new(stages, vox_registry, geo_registry, asset_registry, workers)
+    }
+
+    // Meshes the chunk at `center` with every neighbor far enough along
+    fn request_area(
+        pipeline: &mut ChunkPipeline<BlockData, BlockRegistry, Stages>,
+        center: ChunkPos,
+        target: ChunkStage,
+    ) {
+        for x in -3i32..=3 {
+            for y in -3i32..=3 {
+                for z in -3i32..=3 {
+                    let distance = x.abs().max(y.abs()).max(z.abs());
+                    let stage = match distance {
+                        0 => target,
+                        1 => ChunkStage::Lit,
+                        2 => ChunkStage::Decorated,
+                        _ => ChunkStage::Generated,
+                    };
+                    pipeline.request(
+                        ChunkPos::new(center.x + x, center.y + y, center.z + z),
+                        stage.min(target),
+                    );
+                }
+            }
+        }
+    }
+
+    fn stages(workers: usize) {
+        let mut pipeline = pipeline(Stages::default(), workers);
+        let center = ChunkPos::new(0, 0, 0);
+        request_area(&mut pipeline, center, ChunkStage::Meshed);
+        let meshes = pipeline.flush();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].pos, center);
+        assert_eq!(pipeline.stage(center), ChunkStage::Meshed);
+        assert_eq!(pipeline.stage(ChunkPos::new(1, 0, 0)), ChunkStage::Lit);
+        assert_eq!(
+            pipeline.stage(ChunkPos::new(0, 2, 0)),
+            ChunkStage::Decorated
+        );
+        assert_eq!(
+            pipeline.stage(ChunkPos::new(0, 0, 3)),
+            ChunkStage::Generated
+        );
+        assert!(pipeline.is_idle());
+
+        pipeline.invalidate(center, ChunkStage::Decorated);
+        assert_eq!(pipeline.stage(center), ChunkStage::Decorated);
+        assert_eq!(pipeline.flush().len(), 1);
+        assert_eq!(pipeline.stage(center), ChunkStage::Meshed);
+    }
+
+    #[test]
+    fn stages_inline() {
+        stages(0);
+    }
+
+    #[test]
+    fn stages_threaded() {
+        stages(2);
+    }
+
+    #[test]
+    fn panicking_stage_fails_chunk() {
+        for workers in [0, 2] {
+            let pos = ChunkPos::new(0, 0, 0);
+            let mut pipeline = pipeline(
+                Stages {
+                    panic_at: Some(pos),
+                    ..Default::default()
+                },
+                workers,
+            );
+            pipeline.request(pos, ChunkStage::Generated);
+            pipeline.request(ChunkPos::new(1, 0, 0), ChunkStage::Generated);
+            assert!(pipeline.flush().is_empty());
+            assert!(pipeline.has_failed(pos));
+            assert_eq!(pipeline.stage(pos), ChunkStage::Empty);
+            assert_eq!(
+                pipeline.stage(ChunkPos::new(1, 0, 0)),
+                ChunkStage::Generated
+            );
+            assert!(pipeline.is_idle());
+        }
+    }
+
+    #[test]
+    fn invalidated_mesh_is_dropped() {
+        let open = Arc::new(AtomicBool::new(false));
+        let mut pipeline = pipeline(
+            Stages {
+                open: open.clone(),
+                ..Default::default()
+            },
+            1,
+        );
+        let center = ChunkPos::new(0, 0, 0);
+        request_area(&mut pipeline, center, ChunkStage::Lit);
+        request_area(&mut pipeline, GATED, ChunkStage::Generated);
+        pipeline.flush();
+
+        // The single worker is stuck decorating `GATED` so the mesh job waits behind it
+        pipeline.request(GATED, ChunkStage::Decorated);
+        pipeline.update();
+        pipeline.request(center, ChunkStage::Meshed);
+        assert!(pipeline.update().is_empty());
+        pipeline.invalidate(center, ChunkStage::Lit);
+        open.store(true, Ordering::Release);
+
+        // Only the mesh built after invalidating comes back
+        assert_eq!(pipeline.flush().len(), 1);
+        assert_eq!(pipeline.stage(center), ChunkStage::Meshed);
+    }
+
+    #[test]
+    fn stale_result_is_dropped() {
+        let open = Arc::new(AtomicBool::new(false));
+        let mut pipeline = pipeline(
+            Stages {
+                open: open.clone(),
+                ..Default::default()
+            },
+            1,
+        );
+        request_area(&mut pipeline, GATED, ChunkStage::Generated);
+        pipeline.flush();
+        pipeline.request(GATED, ChunkStage::Decorated);
+        pipeline.update();
+
+        // Replace the chunk while its decoration job is still running
+        pipeline.remove(GATED);
+        let mut chunk = ChunkData::default();
+        chunk.set(RelativeVoxelPos::new(1, 1, 1), block("glass"));
+        pipeline.insert(GATED, chunk, ChunkStage::Generated, ChunkStage::Generated);
+        open.store(true, Ordering::Release);
+        pipeline.flush();
+
+        assert_eq!(
+            pipeline
+                .chunk(GATED)
+                .unwrap()
+                .get_identifier(RelativeVoxelPos::new(1, 1, 1)),
+            "vinox:glass"
+        );
+        assert_eq!(pipeline.stage(GATED), ChunkStage::Generated);
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::JoinHandle,
+};
+
+type Job<T> = Box<dyn FnOnce() -> T + Send>;
+
+/// A fixed size pool of std threads that runs jobs and hands their results back in completion order.
+/// A pool with 0 workers runs every job inline when it is spawned which is useful for headless tests.
+/// A job that panics doesn't take its worker down with it. Its result is the panic payload as an Err instead
+pub struct WorkerPool<T: Send + 'static> {
+    jobs: Option<Sender<Job<T>>>,
+    results: Receiver<std::thread::Result<T>>,
+    result_sender: Sender<std::thread::Result<T>>,
+    workers: Vec<JoinHandle<()>>,
+    pending: usize,
+}
+
+impl<T: Send + 'static> WorkerPool<T> {
+    pub fn new(workers: usize) -> Self {
+        let (job_sender, job_receiver) = channel::<Job<T>>();
+        let (result_sender, results) = channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let workers = (0..workers)
+            .map(|_| {
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                std::thread::spawn(move || loop {
+                    // Only hold the lock while waiting for a job not while running it
+                    let job = match job_receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    let Ok(job) = job else {
+                        return;
+                    };
+                    if result_sender
+                        .send(catch_unwind(AssertUnwindSafe(job)))
+                        .is_err()
+                    {
+                        return;
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            jobs: Some(job_sender),
+            results,
+            result_sender,
+            workers,
+            pending: 0,
+        }
+    }
+
+    /// Queues a job. If the pool has no workers the job is ran immediately on this thread
+    pub fn spawn(&mut self, job: impl FnOnce() -> T + Send + 'static) {
+        self.pending += 1;
+        if self.workers.is_empty() {
+            self.result_sender
+                .send(catch_unwind(AssertUnwindSafe(job)))
+                .expect("Worker pool result receiver dropped");
+        } else if let Some(jobs) = &self.jobs {
+            jobs.send(Box::new(job))
+                .expect("Worker pool threads have all exited");
+        }
+    }
+
+    /// Returns a finished result if there is one without blocking
+    pub fn try_recv(&mut self) -> Option<std::thread::Result<T>> {
+        let result = self.results.try_recv().ok();
+        if result.is_some() {
+            self.pending -= 1;
+        }
+        result
+    }
+
+    /// Blocks until a job finishes. Returns None if nothing is pending
+    pub fn recv(&mut self) -> Option<std::thread::Result<T>> {
+        if self.pending == 0 {
+            return None;
+        }
+        let result = self.results.recv().ok();
+        if result.is_some() {
+            self.pending -= 1;
+        }
+        result
+    }
+
+    /// Returns every result that is currently finished without blocking
+    pub fn drain(&mut self) -> Vec<std::thread::Result<T>> {
+        let mut results = Vec::new();
+        while let Some(result) = self.try_recv() {
+            results.push(result);
+        }
+        results
+    }
+
+    /// Amount of jobs that have been spawned but whose results haven't been received yet
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+}
+
+impl<T: Send + 'static> Drop for WorkerPool<T> {
+    fn drop(&mut self) {
+        // Closing the job channel makes every worker exit its loop
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn panics(workers: usize) {
+        let mut pool = WorkerPool::new(workers);
+        pool.spawn(|| -> u32 { panic!("job failed") });
+        assert!(pool.recv().unwrap().is_err());
+        assert_eq!(pool.pending(), 0);
+        assert!(pool.recv().is_none());
+
+        // The worker survived the panic
+        pool.spawn(|| 7);
+        assert_eq!(pool.recv().unwrap().unwrap(), 7);
+    }
+
+    #[test]
+    fn panic_inline() {
+        panics(0);
+    }
+
+    #[test]
+    fn panic_threaded() {
+        panics(1);
+    }
+
+    #[test]
+    fn drain_collects_every_job() {
+        let mut pool = WorkerPool::new(2);
+        for i in 0..16 {
+            pool.spawn(move || i);
+        }
+        let mut results = Vec::new();
+        while let Some(result) = pool.recv() {
+            results.push(result.unwrap());
+        }
+        results.sort();
+        assert_eq!(results, (0..16).collect::<Vec<_>>());
+        assert!(pool.drain().is_empty());
+    }
+}
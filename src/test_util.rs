@@ -0,0 +1,83 @@
+use ahash::HashMap;
+
+use crate::prelude::*;
+
+/// Stone, glass, a slab and air with a 16x16 texture for every visible block on a 64x64 atlas
+pub fn registries() -> (BlockRegistry, GeometryRegistry, AssetRegistry) {
+    let mut registry = BlockRegistry::default();
+    for (name, geometry, visibility) in [
+        ("stone", BlockGeometry::Block, VoxelVisibility::Opaque),
+        ("glass", BlockGeometry::Block, VoxelVisibility::Transparent),
+        ("slab", BlockGeometry::Slab, VoxelVisibility::Opaque),
+        ("air", BlockGeometry::Block, VoxelVisibility::Empty),
+    ] {
+        registry.insert(
+            format!("vinox:{name}"),
+            Block {
+                identifier: format!("vinox:{name}"),
+                geometry: Some(geometry),
+                visibility: Some(visibility),
+                ..Default::default()
+            },
+        );
+    }
+
+    let mut geo_registry = GeometryRegistry(HashMap::default());
+    geo_registry.insert("vinox:block".to_string(), Geometry::default());
+    let mut slab = Geometry {
+        name: "slab".into(),
+        blocks: [false, false, true, false, false, false],
+        ..Default::default()
+    };
+    slab.element.cubes[0].end = (16, 8, 16);
+    geo_registry.insert("vinox:slab".to_string(), slab);
+
+    let mut texture_uvs = HashMap::default();
+    for name in ["stone", "glass", "slab"] {
+        texture_uvs.insert(
+            format!("vinox:{name}"),
+            [UVRect {
+                x: 0.,
+                y: 0.,
+                w: 16.,
+                h: 16.,
+            }; 6],
+        );
+    }
+    let asset_registry = AssetRegistry {
+        texture_uvs,
+        texture_size: mint::Point2 { x: 64., y: 64. },
+        texture_layers: HashMap::default(),
+    };
+    (registry, geo_registry, asset_registry)
+}
+
+pub fn block(name: &str) -> BlockData {
+    BlockData::new("vinox".into(), name.into())
+}
+
+/// Rock that is a full block 3 times out of 4 and otherwise a slab with its own textures
+pub fn rock_registries() -> (BlockRegistry, GeometryRegistry, AssetRegistry) {
+    let (mut vox_registry, geo_registry, mut asset_registry) = registries();
+    let rock: Block = ron::from_str(
+        r#"(
+            identifier: "vinox:rock",
+            geometry: Some(Block),
+            visibility: Some(Opaque),
+            variants: Some([
+                (weight: Some(3)),
+                (weight: Some(1), geometry: Some(Slab), textures: Some((None, None, None, None, None, None))),
+            ]),
+        )"#,
+    )
+    .unwrap();
+    assert_eq!(rock.variant_weights(), vec![3, 1]);
+    vox_registry.insert("vinox:rock".into(), rock);
+    let uvs = asset_registry.texture_uvs["vinox:stone"];
+    asset_registry.texture_uvs.insert("vinox:rock".into(), uvs);
+    asset_registry.texture_uvs.insert(
+        variant_texture_key("vinox:rock", 1),
+        [UVRect { x: 16., ..uvs[0] }; 6],
+    );
+    (vox_registry, geo_registry, asset_registry)
+}